@@ -0,0 +1,156 @@
+use super::lexer::Lexer;
+use super::parser::Parser;
+use super::preprocessor::Preprocessor;
+use super::generator::Generator;
+use super::diagnostic::Diagnostic;
+use super::source::SourceMap;
+use super::xml::Format;
+use super::util::{get_include_path, include_names};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+// The compiler as a library. Nothing in here prints, exits or writes files, the generated documents and the diagnostics
+// are returned to the caller instead.
+
+// Where included files come from. `resolve` turns the path given to `#include` into the name of a file, which is what
+// the file is called in diagnostics and what `load` is given.
+pub trait FileLoader {
+    fn resolve(&self, path: &str) -> Option<String>;
+    fn load(&self, name: &str) -> io::Result<String>;
+}
+
+// Loads includes from the library directory or the working directory, like the command line tool does. Both loaders
+// find an include of "lib" in a file called "lib" or "lib.gui".
+#[derive(Debug, Default)]
+pub struct FsLoader;
+
+impl FileLoader for FsLoader {
+    fn resolve(&self, path: &str) -> Option<String> {
+        get_include_path(path)
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        fs::read_to_string(name)
+    }
+}
+
+// Loads includes from a set of in-memory files
+#[derive(Debug, Default)]
+pub struct MemoryLoader {
+    pub files: HashMap<String, String>
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, name: &str, content: &str) -> Self {
+        self.files.insert(name.to_string(), content.to_string());
+        self
+    }
+}
+
+impl FileLoader for MemoryLoader {
+    fn resolve(&self, path: &str) -> Option<String> {
+        include_names(path).into_iter().find(|name| self.files.contains_key(name))
+    }
+
+    fn load(&self, name: &str) -> io::Result<String> {
+        match self.files.get(name) {
+            Some(content) => Ok(content.clone()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no file called '{}'", name)))
+        }
+    }
+}
+
+pub struct Options {
+    // The name of the compiled file. `@root` is named after its stem, and diagnostics refer to the file by it.
+    pub filename: String,
+    pub loader: Box<dyn FileLoader>,
+    pub format: Format
+}
+
+impl Options {
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            loader: Box::new(FsLoader),
+            format: Format::default()
+        }
+    }
+
+    pub fn with_loader(mut self, loader: impl FileLoader + 'static) -> Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Output {
+    // The generated documents, keyed by the name of the root or the class of the template they were generated from
+    pub documents: HashMap<String, String>,
+    // Diagnostics that didn't stop the compilation
+    pub warnings: Vec<Diagnostic>
+}
+
+// Keeps the source map of the last compilation around, which is needed to render its diagnostics
+#[derive(Debug, Default)]
+pub struct Compiler {
+    pub source_map: SourceMap
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compile(&mut self, source: &str, options: &Options) -> Result<Output, Vec<Diagnostic>> {
+        self.source_map = SourceMap::new();
+        let file = self.source_map.add(options.filename.clone(), source.to_string(), None);
+
+        // Lexer errors don't stop the parser, so both can be reported at once
+        let mut lexer = Lexer::new(source.to_string(), file);
+        let mut errors = lexer.lex(false).err().unwrap_or_default();
+
+        let mut parser = Parser::new(lexer.tokens, file, options.filename.clone());
+        errors.extend(parser.parse().err().unwrap_or_default());
+        // Warnings are returned together with the errors if there are any, so they aren't lost
+        let mut warnings = std::mem::take(&mut parser.warnings);
+        if !errors.is_empty() {
+            errors.append(&mut warnings);
+            return Err(errors);
+        }
+
+        let mut preprocessor = Preprocessor::new();
+        let preprocessed = preprocessor.preprocess(parser.statements, &mut self.source_map, options.loader.as_ref());
+        warnings.append(&mut preprocessor.warnings);
+        if let Err(mut errors) = preprocessed {
+            errors.append(&mut warnings);
+            return Err(errors);
+        }
+
+        let mut generator = Generator::new(preprocessor.statements, options.format.clone());
+        match generator.generate() {
+            Ok(()) => Ok(Output {
+                documents: generator.documents,
+                warnings
+            }),
+            Err(err) => {
+                warnings.push(err);
+                Err(warnings)
+            }
+        }
+    }
+}
+
+// Compiles a file in one go. Use a `Compiler` instead to render the diagnostics, which needs the source map.
+pub fn compile(source: &str, options: &Options) -> Result<Output, Vec<Diagnostic>> {
+    Compiler::new().compile(source, options)
+}
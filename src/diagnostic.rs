@@ -0,0 +1,386 @@
+use super::util::{get_position_from_char_index, closest_match};
+use super::source::{SourceMap, FileId, Span};
+
+// Diagnostics are what every phase reports problems with. A diagnostic has a primary label pointing at the problem,
+// optionally secondary labels pointing at related code, and notes and help that are printed below the snippet.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+// Every diagnostic has a code, so it can be looked up with `gtk-ui --explain`. Codes are stable, new ones are only ever
+// added to the end of their group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    // Lexer
+    UnterminatedString,
+    InvalidEscape,
+    InvalidNumber,
+    UnrecognizedCharacter,
+    UnterminatedComment,
+    UnknownDirective,
+    InvalidTranslatable,
+    // Parser
+    UnexpectedToken,
+    UnclosedBlock,
+    MisplacedStatement,
+    DanglingDocComment,
+    MixedDefinition,
+    InvalidPropertyDefinition,
+    InvalidDirectiveArguments,
+    InvalidValue,
+    InvalidSignal,
+    MalformedBinding,
+    InvalidMenu,
+    InvalidTemplate,
+    // Preprocessor
+    IncludeNotFound,
+    RecursiveInclude,
+    IncludeNotReadable,
+    // Generator
+    TypeMismatch,
+    InvalidInlineValue,
+    UnknownProperty,
+    ArgumentCount,
+    InvalidInheritance,
+    InvalidChild,
+    UnknownSignal,
+    InvalidBinding,
+    InvalidReference,
+    UnknownChildType,
+    InvalidStyle,
+    UnknownRole,
+    DuplicateId,
+    DuplicateDocument,
+    UnknownDefinition,
+    Internal
+}
+
+pub const CODES: &[Code] = &[
+    Code::UnterminatedString,
+    Code::InvalidEscape,
+    Code::InvalidNumber,
+    Code::UnrecognizedCharacter,
+    Code::UnterminatedComment,
+    Code::UnknownDirective,
+    Code::InvalidTranslatable,
+    Code::UnexpectedToken,
+    Code::UnclosedBlock,
+    Code::MisplacedStatement,
+    Code::DanglingDocComment,
+    Code::MixedDefinition,
+    Code::InvalidPropertyDefinition,
+    Code::InvalidDirectiveArguments,
+    Code::InvalidValue,
+    Code::InvalidSignal,
+    Code::MalformedBinding,
+    Code::InvalidMenu,
+    Code::InvalidTemplate,
+    Code::IncludeNotFound,
+    Code::RecursiveInclude,
+    Code::IncludeNotReadable,
+    Code::TypeMismatch,
+    Code::InvalidInlineValue,
+    Code::UnknownProperty,
+    Code::ArgumentCount,
+    Code::InvalidInheritance,
+    Code::InvalidChild,
+    Code::UnknownSignal,
+    Code::InvalidBinding,
+    Code::InvalidReference,
+    Code::UnknownChildType,
+    Code::InvalidStyle,
+    Code::UnknownRole,
+    Code::DuplicateId,
+    Code::DuplicateDocument,
+    Code::UnknownDefinition,
+    Code::Internal
+];
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::UnterminatedString => "E0001",
+            Code::InvalidEscape => "E0002",
+            Code::InvalidNumber => "E0003",
+            Code::UnrecognizedCharacter => "E0004",
+            Code::UnterminatedComment => "E0005",
+            Code::UnknownDirective => "E0006",
+            Code::InvalidTranslatable => "E0007",
+            Code::UnexpectedToken => "E0100",
+            Code::UnclosedBlock => "E0101",
+            Code::MisplacedStatement => "E0102",
+            Code::DanglingDocComment => "E0103",
+            Code::MixedDefinition => "E0104",
+            Code::InvalidPropertyDefinition => "E0105",
+            Code::InvalidDirectiveArguments => "E0106",
+            Code::InvalidValue => "E0107",
+            Code::InvalidSignal => "E0108",
+            Code::MalformedBinding => "E0109",
+            Code::InvalidMenu => "E0110",
+            Code::InvalidTemplate => "E0111",
+            Code::IncludeNotFound => "E0200",
+            Code::RecursiveInclude => "E0201",
+            Code::IncludeNotReadable => "E0202",
+            Code::TypeMismatch => "E0300",
+            Code::InvalidInlineValue => "E0301",
+            Code::UnknownProperty => "E0302",
+            Code::ArgumentCount => "E0303",
+            Code::InvalidInheritance => "E0304",
+            Code::InvalidChild => "E0305",
+            Code::UnknownSignal => "E0306",
+            Code::InvalidBinding => "E0307",
+            Code::InvalidReference => "E0308",
+            Code::UnknownChildType => "E0309",
+            Code::InvalidStyle => "E0310",
+            Code::UnknownRole => "E0311",
+            Code::DuplicateId => "E0312",
+            Code::DuplicateDocument => "E0313",
+            Code::UnknownDefinition => "E0314",
+            Code::Internal => "E0999"
+        }
+    }
+
+    pub fn from(code: &str) -> Option<Code> {
+        CODES.iter().find(|known| known.as_str().eq_ignore_ascii_case(code)).copied()
+    }
+
+    // The long-form explanation printed by `--explain`
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Code::UnterminatedString => include_str!("explanations/E0001.md"),
+            Code::InvalidEscape => include_str!("explanations/E0002.md"),
+            Code::InvalidNumber => include_str!("explanations/E0003.md"),
+            Code::UnrecognizedCharacter => include_str!("explanations/E0004.md"),
+            Code::UnterminatedComment => include_str!("explanations/E0005.md"),
+            Code::UnknownDirective => include_str!("explanations/E0006.md"),
+            Code::InvalidTranslatable => include_str!("explanations/E0007.md"),
+            Code::UnexpectedToken => include_str!("explanations/E0100.md"),
+            Code::UnclosedBlock => include_str!("explanations/E0101.md"),
+            Code::MisplacedStatement => include_str!("explanations/E0102.md"),
+            Code::DanglingDocComment => include_str!("explanations/E0103.md"),
+            Code::MixedDefinition => include_str!("explanations/E0104.md"),
+            Code::InvalidPropertyDefinition => include_str!("explanations/E0105.md"),
+            Code::InvalidDirectiveArguments => include_str!("explanations/E0106.md"),
+            Code::InvalidValue => include_str!("explanations/E0107.md"),
+            Code::InvalidSignal => include_str!("explanations/E0108.md"),
+            Code::MalformedBinding => include_str!("explanations/E0109.md"),
+            Code::InvalidMenu => include_str!("explanations/E0110.md"),
+            Code::InvalidTemplate => include_str!("explanations/E0111.md"),
+            Code::IncludeNotFound => include_str!("explanations/E0200.md"),
+            Code::RecursiveInclude => include_str!("explanations/E0201.md"),
+            Code::IncludeNotReadable => include_str!("explanations/E0202.md"),
+            Code::TypeMismatch => include_str!("explanations/E0300.md"),
+            Code::InvalidInlineValue => include_str!("explanations/E0301.md"),
+            Code::UnknownProperty => include_str!("explanations/E0302.md"),
+            Code::ArgumentCount => include_str!("explanations/E0303.md"),
+            Code::InvalidInheritance => include_str!("explanations/E0304.md"),
+            Code::InvalidChild => include_str!("explanations/E0305.md"),
+            Code::UnknownSignal => include_str!("explanations/E0306.md"),
+            Code::InvalidBinding => include_str!("explanations/E0307.md"),
+            Code::InvalidReference => include_str!("explanations/E0308.md"),
+            Code::UnknownChildType => include_str!("explanations/E0309.md"),
+            Code::InvalidStyle => include_str!("explanations/E0310.md"),
+            Code::UnknownRole => include_str!("explanations/E0311.md"),
+            Code::DuplicateId => include_str!("explanations/E0312.md"),
+            Code::DuplicateDocument => include_str!("explanations/E0313.md"),
+            Code::UnknownDefinition => include_str!("explanations/E0314.md"),
+            Code::Internal => include_str!("explanations/E0999.md")
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>
+}
+
+impl Diagnostic {
+    // A diagnostic that isn't about a specific place in the file
+    pub fn new(severity: Severity, code: Code, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None
+        }
+    }
+
+    pub fn error(code: Code, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic::new(Severity::Error, code, message).with_primary(span, "")
+    }
+
+    pub fn warning(code: Code, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic::new(Severity::Warning, code, message).with_primary(span, "")
+    }
+
+    // Replaces the primary label, so its message can be set after `error()` or `warning()`
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.retain(|label| !label.primary);
+        self.labels.insert(0, Label { span, message: message.into(), primary: true });
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), primary: false });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    // Suggests the candidate closest to a misspelled name, if any of them is close enough
+    pub fn with_suggestion<'a>(self, name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+        match closest_match(name, candidates) {
+            Some(suggestion) => self.with_help(format!("did you mean '{}'?", suggestion)),
+            None => self
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        self.labels.iter().find(|label| label.primary).map(|label| &label.span)
+    }
+
+    // Renders the diagnostic with the lines its labels point at, in the style of rustc:
+    //
+    // error[E0302]: no such property on 'GtkLabel' called 'nope'
+    //  --> main.gui:6:13
+    //   |
+    // 6 |             .nope(1)
+    //   |             ^^^^^
+    //   |
+    //   = help: did you mean 'name'?
+    //
+    // Labels in other files than the primary label get their own snippet, and if the primary label is in an included
+    // file, the include chain is noted at the end.
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        let (name, color) = match self.severity {
+            Severity::Error => ("error", "\x1b[1;31m"),
+            Severity::Warning => ("warning", "\x1b[1;33m")
+        };
+        let mut result = format!("{color}{name}[{}]\x1b[0m\x1b[1m: {}\x1b[0m\n", self.code.as_str(), self.message);
+
+        // Labels are grouped by file, the primary label's file first
+        let mut files: Vec<FileId> = Vec::new();
+        for label in self.labels.iter().filter(|label| label.primary).chain(self.labels.iter().filter(|label| !label.primary)) {
+            if !files.contains(&label.span.file) {
+                files.push(label.span.file);
+            }
+        }
+
+        let mut positioned: Vec<(FileId, usize, usize, &Label)> = self.labels.iter()
+            .filter_map(|label| {
+                let content = &source_map.get(label.span.file).content;
+                let (line, column) = get_position_from_char_index(label.span.range.start, content).ok()?;
+                Some((label.span.file, line, column, label))
+            })
+            .collect();
+        positioned.sort_by_key(|(_, line, column, _)| (*line, *column));
+
+        let gutter = positioned.iter().map(|(_, line, _, _)| line.to_string().len()).max().unwrap_or(1);
+        let padding = " ".repeat(gutter);
+        let blue = "\x1b[1;34m";
+        let reset = "\x1b[0m";
+
+        for (index, file) in files.iter().enumerate() {
+            let source = source_map.get(*file);
+            let labels: Vec<(usize, usize, &Label)> = positioned.iter()
+                .filter(|(label_file, _, _, _)| label_file == file)
+                .map(|(_, line, column, label)| (*line, *column, *label))
+                .collect();
+
+            let arrow = if index == 0 { "-->" } else { ":::" };
+            match labels.iter().find(|(_, _, label)| label.primary).or(labels.first()) {
+                Some((line, column, _)) => result += format!("{padding}{blue}{arrow}{reset} {}:{line}:{column}\n", source.name).as_str(),
+                None => result += format!("{padding}{blue}{arrow}{reset} {}\n", source.name).as_str()
+            }
+            if labels.is_empty() {
+                continue;
+            }
+
+            let lines: Vec<&str> = source.content.split('\n').collect();
+            result += format!("{padding} {blue}|{reset}\n").as_str();
+
+            let mut previous_line: Option<usize> = None;
+            for (index, (line, _, _)) in labels.iter().enumerate() {
+                if previous_line == Some(*line) {
+                    continue;
+                }
+                if previous_line.is_some_and(|previous| line - previous > 1) {
+                    result += format!("{blue}...{reset}\n").as_str();
+                }
+                previous_line = Some(*line);
+
+                let text = lines[line - 1].trim_end_matches('\r');
+                result += format!("{blue}{line:>gutter$} |{reset} {text}\n").as_str();
+
+                for (_, column, label) in labels[index..].iter().take_while(|(other, _, _)| other == line) {
+                    // Tabs are kept in the indentation so the markers line up with the text above them
+                    let indentation: String = text.chars()
+                        .take(column - 1)
+                        .map(|c| if c == '\t' { '\t' } else { ' ' })
+                        .collect();
+                    // A label spanning several lines is only underlined up to the end of its first line
+                    let range = &label.span.range;
+                    let line_start = source.content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+                    let end = range.end.min(line_start + text.len()).max(range.start);
+                    let width = source.content[range.start..end].chars().count().max(1);
+                    let (marker, marker_color) = if label.primary { ('^', color) } else { ('-', blue) };
+                    let markers: String = std::iter::repeat_n(marker, width).collect();
+                    let message = if label.message.is_empty() { String::new() } else { format!(" {}", label.message) };
+                    result += format!("{padding} {blue}|{reset} {indentation}{marker_color}{markers}{message}{reset}\n").as_str();
+                }
+            }
+        }
+
+        let mut notes: Vec<String> = Vec::new();
+        if let Some(span) = self.span() {
+            for include in source_map.include_chain(span.file) {
+                let including = source_map.get(include.file);
+                match get_position_from_char_index(include.range.start, &including.content) {
+                    Ok((line, _)) => notes.push(format!("included from {}:{}", including.name, line)),
+                    Err(_) => notes.push(format!("included from {}", including.name))
+                }
+            }
+        }
+        notes.extend(self.notes.iter().cloned());
+
+        if !notes.is_empty() || self.help.is_some() {
+            result += format!("{padding} {blue}|{reset}\n").as_str();
+        }
+        for note in &notes {
+            result += format!("{padding} {blue}={reset} \x1b[1mnote\x1b[0m: {note}\n").as_str();
+        }
+        if let Some(help) = &self.help {
+            result += format!("{padding} {blue}={reset} \x1b[1mhelp\x1b[0m: {help}\n").as_str();
+        }
+        result
+    }
+}
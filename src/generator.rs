@@ -2,185 +2,774 @@ use super::parser::{
     Statement,
     StatementValue,
     DefinitionType,
+    SetterValue,
     Setter,
-    Definition
+    Arguments,
+    ElementDefinition,
+    Binding,
+    Signal,
+    ChildType,
+    Definition,
+    Literal,
+    Value,
+    Object,
+    Menu
 };
 use super::lexer::{
     DefinitionType as TokenDefinitionType,
-    TypeIdentifierType as TokenTypeIdentifierType,
-    Token,
-    TokenValue,
+    TypeIdentifierType as TokenTypeIdentifierType
 };
-use std::fs::File;
-use std::io::Write;
+use super::diagnostic::{Diagnostic, Code};
 use std::collections::HashMap;
-use std::ops::Range;
+use super::source::Span;
+use super::xml::{Element, Node, Format};
 
 #[derive(Debug)]
 pub struct CachedRawDefinition {
-    props: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType)>,
-    args: Vec<(String, TokenTypeIdentifierType, TokenDefinitionType)>,
+    // Properties and arguments keep the span of their property definition, to point at it in diagnostics
+    props: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType, Span)>,
+    args: Vec<(String, TokenTypeIdentifierType, TokenDefinitionType, Span)>,
+    layout_props: HashMap<String, (TokenTypeIdentifierType, Span)>,
+    // Accessible properties, relations and states share one namespace, the definition type tells them apart
+    accessible: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType, Span)>,
+    roles: HashMap<String, Span>,
+    elements: HashMap<String, (ElementDefinition, Span)>,
+    signals: HashMap<String, Span>,
+    child_types: HashMap<String, Span>,
     inherits: Vec<String>,
-    range: Range<usize>
+    span: Span
 }
 
 #[derive(Debug)]
 pub enum CachedDefinition {
-    Raw(CachedRawDefinition),
-    Collective(String)
+    Raw(Box<CachedRawDefinition>),
+    Collective(Vec<Element>)
 }
 
 #[derive(Debug)]
 pub struct Generator { 
     statements: Vec<Statement>,
     definitions: HashMap<String, CachedDefinition>,
-    header: String
+    header: Vec<Node>,
+    format: Format,
+    // The generated documents, keyed by the name of the root or the class of the template they were generated from
+    pub documents: HashMap<String, String>
 } 
 
 impl Generator {
 
-    fn is_valid_type(token: &Token, expected_type: &TokenTypeIdentifierType) -> Result<bool, (String, Range<usize>)> {
-        match token.value {
-            TokenValue::Bool(_) => {
-                Ok(matches!(expected_type, TokenTypeIdentifierType::Bool))
-            },
-            TokenValue::Number(_) => {
-                Ok(matches!(expected_type, TokenTypeIdentifierType::Number))
-            },
-            TokenValue::String(_) => {
-                Ok(matches!(expected_type, TokenTypeIdentifierType::String))
-            },
-            _ => Err((format!("{} is not a primitive and therefore it's type cannot be checked", token.to_string()), token.range.clone()))
+    fn check_type(name: &str, literal: &Literal, expected_type: &TokenTypeIdentifierType, declared: &Span) -> Result<(), Diagnostic> {
+        if literal.value.is_of_type(expected_type) {
+            Ok(())
+        } else {
+            let diagnostic = Diagnostic::error(Code::TypeMismatch, format!("'{}' expects {}, found {}", name, expected_type.to_string(), literal.value.to_string()), literal.span.clone())
+                .with_primary(literal.span.clone(), format!("expected {}", expected_type.to_string()))
+                .with_label(declared.clone(), format!("'{}' is declared here", name));
+            match (&literal.value, expected_type) {
+                (Value::String(id), TokenTypeIdentifierType::Object(_)) => Err(diagnostic.with_help(format!("objects are referred to with '&', like '&{}'", id))),
+                _ => Err(diagnostic)
+            }
+        }
+    }
+
+    // Values are written the way GtkBuilder files usually have them, which depends on the declared type rather than on the
+    // literal. An integer given to a Double is written as a double, and doubles keep enough digits to be read back exactly.
+    fn format_value(value: &Value, value_type: &TokenTypeIdentifierType) -> String {
+        match (value, value_type) {
+            (Value::Bool(boolean), _) => boolean.to_string(),
+            (Value::Integer(integer), TokenTypeIdentifierType::Double) => format!("{:?}", *integer as f64),
+            (Value::Float(float), _) => format!("{:?}", float),
+            _ => value.value_to_string()
+        }
+    }
+
+    fn inline_value(name: &str, literal: &Literal, value_type: &TokenTypeIdentifierType) -> Result<(String, String), Diagnostic> {
+        if let Value::Translatable(_) = literal.value {
+            Err(
+                Diagnostic::error(Code::InvalidInlineValue, format!("'{}' is an inline value and cannot be translatable", name), literal.span.clone())
+                    .with_note("inline values are written as XML attributes, which can't be translated")
+            )
+        } else {
+            Ok((name.to_string(), Generator::format_value(&literal.value, value_type)))
+        }
+    }
+
+    // A <property>, with the translatable, context and comments attributes for translatable strings
+    fn property(name: &str, value: &Value, value_type: &TokenTypeIdentifierType) -> Element {
+        Generator::value_element("property", name, value, value_type)
+    }
+
+    // An element that holds a value as its text, like <property> or the <relation> and <state> of <accessibility>
+    fn value_element(tag: &str, name: &str, value: &Value, value_type: &TokenTypeIdentifierType) -> Element {
+        let property = Element::new(tag).with_attribute("name", name);
+        Generator::translatable(property, value).with_text(&Generator::format_value(value, value_type))
+    }
+
+    // The translatable, context and comments attributes of an element whose text is a translatable string
+    fn translatable(mut element: Element, value: &Value) -> Element {
+        if let Value::Translatable(translatable) = value {
+            element = element.with_attribute("translatable", "yes");
+            if let Some(context) = &translatable.context {
+                element = element.with_attribute("context", context);
+            }
+            if let Some(comments) = &translatable.comments {
+                element = element.with_attribute("comments", comments);
+            }
+        }
+        element
+    }
+
+    fn signal(signal: &Signal) -> Element {
+        let mut element = Element::new("signal")
+            .with_attribute("name", &signal.name)
+            .with_attribute("handler", &signal.handler);
+        if let Some(swapped) = signal.swapped {
+            element = element.with_attribute("swapped", if swapped { "yes" } else { "no" });
+        }
+        if let Some(after) = signal.after {
+            element = element.with_attribute("after", if after { "yes" } else { "no" });
+        }
+        if let Some((object, _)) = &signal.object {
+            element = element.with_attribute("object", object);
+        }
+        element
+    }
+
+    // A <menu>, <section>, <submenu> or <item> with its attributes. A target that isn't a string is given the GVariant
+    // type of its value.
+    fn menu(menu: &Menu) -> Element {
+        let mut element = Element::new(menu.kind.to_string());
+        if let Some(id) = &menu.id {
+            element = element.with_attribute("id", id);
+        }
+        for (name, literal) in &menu.attributes {
+            let (value_type, variant_type) = match literal.value {
+                Value::Integer(_) => (TokenTypeIdentifierType::Int, Some("i")),
+                Value::Float(_) => (TokenTypeIdentifierType::Double, Some("d")),
+                Value::Bool(_) => (TokenTypeIdentifierType::Bool, Some("b")),
+                _ => (TokenTypeIdentifierType::String, None)
+            };
+            let mut attribute = Generator::value_element("attribute", name, &literal.value, &value_type);
+            if let Some(variant_type) = variant_type {
+                attribute = attribute.with_attribute("type", variant_type);
+            }
+            element = element.with_child(attribute);
+        }
+        for child in &menu.children {
+            element = element.with_child(Generator::menu(child));
+        }
+        element
+    }
+
+    // Only setters of element definitions take more than one argument or named arguments
+    fn arguments_error(name: &str, arguments: &Arguments) -> Diagnostic {
+        match arguments.named.first() {
+            Some((_, span, _)) => Diagnostic::error(Code::InvalidValue, format!("'{}' doesn't take named arguments", name), span.clone()),
+            None => Diagnostic::error(Code::InvalidValue, format!("expected 1 argument, got {}", arguments.positional.len()), arguments.span.clone())
+        }
+    }
+
+    // An item added by the setter of an element definition. The text and the required attributes are given in order,
+    // optional attributes are given by name.
+    fn element(&self, element: &ElementDefinition, declared: &Span, setter: &Setter, ids: &HashMap<String, (String, Span)>) -> Result<Element, Diagnostic> {
+        let (positional, named, span) = match &setter.value {
+            SetterValue::Literal(literal) => (std::slice::from_ref(literal), &[][..], &literal.span),
+            SetterValue::Arguments(arguments) => (arguments.positional.as_slice(), arguments.named.as_slice(), &arguments.span),
+            SetterValue::Binding(_) | SetterValue::Object(_) => return Err(
+                Diagnostic::error(Code::InvalidValue, format!("'{}' adds an element and can only be given literals", setter.name), setter.span.clone())
+                    .with_label(declared.clone(), format!("'{}' is declared here", setter.name))
+            )
+        };
+
+        let mut expected: Vec<(&str, &TokenTypeIdentifierType)> = Vec::new();
+        if let Some(text) = &element.text {
+            expected.push(("text", text));
+        }
+        expected.extend(element.attributes.iter().filter(|attribute| !attribute.optional).map(|attribute| (attribute.name.as_str(), &attribute.value_type)));
+        if positional.len() != expected.len() {
+            return Err(
+                Diagnostic::error(Code::ArgumentCount, format!("'{}' expects {} args, {} given", setter.name, expected.len(), positional.len()), span.clone())
+                    .with_label(declared.clone(), format!("'{}' is declared here", setter.name))
+            );
+        }
+
+        let mut item = Element::new(&element.name);
+        let mut values: Vec<(&str, &Literal, &TokenTypeIdentifierType)> = expected.iter().zip(positional).map(|((name, value_type), literal)| (*name, literal, *value_type)).collect();
+        for (name, name_span, literal) in named {
+            let attribute = match element.attributes.iter().find(|attribute| attribute.optional && attribute.name == *name) {
+                Some(attribute) => attribute,
+                None => {
+                    let diagnostic = Diagnostic::error(Code::UnknownProperty, format!("'{}' has no optional attribute called '{}'", setter.name, name), name_span.clone())
+                        .with_label(declared.clone(), format!("'{}' is declared here", setter.name));
+                    let optional = element.attributes.iter().filter(|attribute| attribute.optional).map(|attribute| attribute.name.as_str());
+                    return Err(diagnostic.with_suggestion(name, optional));
+                }
+            };
+            if values.iter().any(|(given, _, _)| *given == name) {
+                return Err(Diagnostic::error(Code::InvalidValue, format!("'{}' is given more than once", name), name_span.clone()));
+            }
+            values.push((name, literal, &attribute.value_type));
+        }
+
+        for (name, literal, value_type) in values {
+            Generator::check_type(name, literal, value_type, declared)?;
+            self.check_reference(name, literal, value_type, declared, ids)?;
+            if element.text.is_some() && name == "text" {
+                item = Generator::translatable(item, &literal.value).with_text(&Generator::format_value(&literal.value, value_type));
+            } else {
+                let (name, value) = Generator::inline_value(name, literal, value_type)?;
+                item = item.with_attribute(&name, &value);
+            }
+        }
+        Ok(item)
+    }
+
+    // Only widgets can be styled
+    fn style(&self, object: &Object) -> Result<Option<Element>, Diagnostic> {
+        let span = match object.classes.first() {
+            Some((_, span)) => span,
+            None => return Ok(None)
+        };
+        if !self.inherits_class(&object.name, "GtkWidget") {
+            return Err(
+                Diagnostic::error(Code::InvalidStyle, format!("'{}' doesn't inherit GtkWidget, so it can't have style classes", object.name), span.clone())
+                    .with_label(object.name_span.clone(), format!("'{}' is not a widget", object.name))
+            );
+        }
+
+        let mut style = Element::new("style");
+        for (class, _) in &object.classes {
+            style = style.with_child(Element::new("class").with_attribute("name", class));
+        }
+        Ok(Some(style))
+    }
+
+    // The names a definition declares with `@LayoutProp`, `@Signal`, `@ChildType` or one of the accessible definitions,
+    // including inherited ones
+    fn declared_names<'a, T: 'a>(&'a self, definition: &'a CachedRawDefinition, declared: fn(&CachedRawDefinition) -> &HashMap<String, T>) -> Vec<&'a str> {
+        let mut names: Vec<&str> = declared(definition).keys().map(String::as_str).collect();
+        for parent_name in &definition.inherits {
+            if let Some(CachedDefinition::Raw(parent_definition)) = self.definitions.get(parent_name) {
+                names.extend(self.declared_names(parent_definition, declared));
+            }
+        }
+        names
+    }
+
+    // Looks up a name a definition declares, in the definition itself or in one it inherits
+    fn lookup<'a, T: 'a>(&'a self, definition: &'a CachedRawDefinition, name: &str, declared: fn(&CachedRawDefinition) -> &HashMap<String, T>) -> Option<&'a T> {
+        declared(definition).get(name).or_else(|| {
+            definition.inherits.iter().find_map(|parent_name| match self.definitions.get(parent_name) {
+                Some(CachedDefinition::Raw(parent_definition)) => self.lookup(parent_definition, name, declared),
+                _ => None
+            })
+        })
+    }
+
+    // A detail after "::", like in "notify::label", isn't part of the signal's name. The object a handler is connected
+    // with has to be in the same definition, like the source of a binding.
+    fn check_signal(&self, definition: &CachedRawDefinition, definition_name: &str, signal: &Signal, ids: &HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        if let Some((object, span)) = &signal.object {
+            if !ids.contains_key(object) {
+                return Err(Generator::unknown_id(Code::InvalidReference, object, span, ids));
+            }
+        }
+
+        let name = signal.name.split("::").next().unwrap_or_default();
+        let names = self.declared_names(definition, |definition| &definition.signals);
+        if names.contains(&name) {
+            return Ok(());
+        }
+
+        let diagnostic = Diagnostic::error(Code::UnknownSignal, format!("no such signal on '{}' called '{}'", definition_name, name), signal.name_span.clone());
+        Err(diagnostic.with_suggestion(name, names))
+    }
+
+    // Internal children aren't declared, so they aren't checked
+    fn check_child_type(&self, definition: &CachedRawDefinition, definition_name: &str, child_type: &ChildType) -> Result<(), Diagnostic> {
+        let names = self.declared_names(definition, |definition| &definition.child_types);
+        if child_type.internal || names.contains(&child_type.name.as_str()) {
+            return Ok(());
+        }
+
+        let diagnostic = Diagnostic::error(Code::UnknownChildType, format!("'{}' doesn't accept children of type '{}'", definition_name, child_type.name), child_type.span.clone());
+        Err(diagnostic.with_suggestion(&child_type.name, names))
+    }
+
+    // A <child>, with the type or internal-child attribute for children that have a child type
+    fn child(child_type: Option<&ChildType>, object: Element) -> Element {
+        let child = match child_type {
+            Some(ChildType { name, internal: true, .. }) => Element::new("child").with_attribute("internal-child", name),
+            Some(ChildType { name, .. }) => Element::new("child").with_attribute("type", name),
+            None => Element::new("child")
+        };
+        child.with_child(object)
+    }
+
+    // Objects directly inside a root or collective definition, or given to a property, have no parent to be the child of
+    fn check_no_parent(children: &[Statement]) -> Result<(), Diagnostic> {
+        for child in children {
+            if let StatementValue::Object(object) = &child.value {
+                if let Some(child_type) = &object.child_type {
+                    return Err(Diagnostic::error(Code::UnknownChildType, format!("'{}' is not a child of an object, so it can't have a child type", child_type.name), child_type.span.clone()));
+                }
+                if let Some(setter) = object.layout.first() {
+                    return Err(Diagnostic::error(Code::UnknownProperty, format!("'{}' is not a child of an object, so it has no layout properties", object.name), setter.span.clone()));
+                }
+            }
         }
+        Ok(())
     }
 
-    fn get_prop_from_definition(&self, definition: &CachedRawDefinition, definition_name: &String, setter: &Setter) -> Result<(TokenTypeIdentifierType, TokenDefinitionType), (String, Range<usize>)> {
-        if let Some(prop) = definition.props.get(setter.name.as_str()) {
+    // The <layout> of a child, its layout properties are declared by the parent
+    fn layout(&self, parent: &CachedRawDefinition, parent_name: &str, object: &Object, ids: &HashMap<String, (String, Span)>) -> Result<Option<Element>, Diagnostic> {
+        if object.layout.is_empty() {
+            return Ok(None);
+        }
+
+        let mut layout = Element::new("layout");
+        for setter in &object.layout {
+            let (expected_type, declared) = match self.lookup(parent, &setter.name, |definition| &definition.layout_props) {
+                Some(layout_prop) => layout_prop,
+                None => {
+                    let diagnostic = Diagnostic::error(Code::UnknownProperty, format!("no such layout property on '{}' called '{}'", parent_name, setter.name), setter.name_span.clone());
+                    return Err(diagnostic.with_suggestion(&setter.name, self.declared_names(parent, |definition| &definition.layout_props)));
+                }
+            };
+            if let SetterValue::Arguments(arguments) = &setter.value {
+                return Err(Generator::arguments_error(&setter.name, arguments));
+            }
+            if let SetterValue::Literal(literal) = &setter.value {
+                Generator::check_type(&setter.name, literal, expected_type, declared)?;
+                self.check_reference(&setter.name, literal, expected_type, declared, ids)?;
+                layout = layout.with_child(Generator::property(&setter.name, &literal.value, expected_type));
+            }
+        }
+        Ok(Some(layout))
+    }
+
+    // `.accessible.role` sets the accessible-role property and takes one of the roles declared with `@AccessibleRole`
+    fn role(&self, definition: &CachedRawDefinition, definition_name: &str, setter: &Setter) -> Result<Element, Diagnostic> {
+        let (role, span) = match &setter.value {
+            SetterValue::Literal(Literal { value: Value::String(role), span }) => (role, span),
+            SetterValue::Literal(literal) => return Err(Diagnostic::error(Code::TypeMismatch, format!("'role' expects String, found {}", literal.value.to_string()), literal.span.clone())),
+            SetterValue::Arguments(arguments) => return Err(Generator::arguments_error("role", arguments)),
+            _ => return Err(Diagnostic::error(Code::TypeMismatch, "'role' expects String", setter.span.clone()))
+        };
+
+        let names = self.declared_names(definition, |definition| &definition.roles);
+        if names.contains(&role.as_str()) {
+            return Ok(Element::new("property").with_attribute("name", "accessible-role").with_text(role));
+        }
+
+        let diagnostic = Diagnostic::error(Code::UnknownRole, format!("no such accessible role on '{}' called '{}'", definition_name, role), span.clone());
+        Err(diagnostic.with_suggestion(role, names))
+    }
+
+    // The <accessibility> of an object. The role isn't part of it, it's a regular property and is returned separately.
+    fn accessibility(&self, definition: &CachedRawDefinition, definition_name: &str, object: &Object, ids: &HashMap<String, (String, Span)>) -> Result<(Option<Element>, Option<Element>), Diagnostic> {
+        let mut role = None;
+        let mut accessibility = Element::new("accessibility");
+        for setter in &object.accessible {
+            if setter.name == "role" {
+                role = Some(self.role(definition, definition_name, setter)?);
+                continue;
+            }
+
+            let (expected_type, definition_type, declared) = match self.lookup(definition, &setter.name, |definition| &definition.accessible) {
+                Some(accessible) => accessible,
+                None => {
+                    let diagnostic = Diagnostic::error(Code::UnknownProperty, format!("no such accessible property, relation or state on '{}' called '{}'", definition_name, setter.name), setter.name_span.clone());
+                    return Err(diagnostic.with_suggestion(&setter.name, self.declared_names(definition, |definition| &definition.accessible)));
+                }
+            };
+            if let SetterValue::Arguments(arguments) = &setter.value {
+                return Err(Generator::arguments_error(&setter.name, arguments));
+            }
+            if let SetterValue::Literal(literal) = &setter.value {
+                Generator::check_type(&setter.name, literal, expected_type, declared)?;
+                self.check_reference(&setter.name, literal, expected_type, declared, ids)?;
+                let tag = match definition_type {
+                    TokenDefinitionType::AccessibleRelation => "relation",
+                    TokenDefinitionType::AccessibleState => "state",
+                    _ => "property"
+                };
+                accessibility = accessibility.with_child(Generator::value_element(tag, &setter.name, &literal.value, expected_type));
+            }
+        }
+
+        if accessibility.children.is_empty() {
+            Ok((role, None))
+        } else {
+            Ok((role, Some(accessibility)))
+        }
+    }
+
+    // The names of every property a definition has, including inherited ones
+    fn property_names<'a>(&'a self, definition: &'a CachedRawDefinition) -> Vec<&'a str> {
+        let mut names: Vec<&str> = definition.props.keys().map(String::as_str).collect();
+        for parent_name in &definition.inherits {
+            if let Some(CachedDefinition::Raw(parent_definition)) = self.definitions.get(parent_name) {
+                names.extend(self.property_names(parent_definition));
+            }
+        }
+        names
+    }
+
+    fn get_prop_from_definition(&self, definition: &CachedRawDefinition, definition_name: &str, name: &str, name_span: &Span) -> Result<(TokenTypeIdentifierType, TokenDefinitionType, Span), Diagnostic> {
+        if let Some(prop) = definition.props.get(name) {
             Ok(prop.clone())
         } else {
             for definition_name in &definition.inherits {
                 if let Some(parent_definition) = self.definitions.get(definition_name) {
                     if let CachedDefinition::Raw(parent_definition) = parent_definition {
-                        if let Ok(result) = self.get_prop_from_definition(parent_definition, definition_name, setter) {
+                        if let Ok(result) = self.get_prop_from_definition(parent_definition, definition_name, name, name_span) {
                             return Ok(result);
                         }
                     } else {
-                        return Err((format!("cannot inherit collective definition '{}'", definition_name), definition.range.clone()))
+                        return Err(Diagnostic::error(Code::InvalidInheritance, format!("cannot inherit collective definition '{}'", definition_name), definition.span.clone()))
                     }
                 } else {
-                    return Err((format!("inherited undefined definition '{}'", definition_name), definition.range.clone()))
+                    return Err(Diagnostic::error(Code::InvalidInheritance, format!("inherited undefined definition '{}'", definition_name), definition.span.clone()))
                 }
             }
-            return Err((format!("no such property on '{}' called '{}'", definition_name, setter.name.as_str()), setter.range.clone()));
+            let diagnostic = Diagnostic::error(Code::UnknownProperty, format!("no such property on '{}' called '{}'", definition_name, name), name_span.clone());
+            Err(diagnostic.with_suggestion(name, self.property_names(definition)))
         }
     }
 
-    pub fn generate_from_collective(&self, children: &Vec<Statement>) -> Result<String, (String, Range<usize>)> {
-        let mut result = String::new();
+    // The ids given to the objects of a definition, with the class of the object they were given to and where. Objects
+    // from a collective definition have already been generated, so their ids are taken from the generated XML and point
+    // at the object that pulled them in.
+    fn object_ids(&self, statements: &[Statement], ids: &mut HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        for statement in statements {
+            if let StatementValue::Menu(menu) = &statement.value {
+                Generator::menu_ids(menu, ids)?;
+            }
+            if let StatementValue::Object(object) = &statement.value {
+                match self.definitions.get(&object.name) {
+                    Some(CachedDefinition::Collective(elements)) => {
+                        for element in elements {
+                            Generator::element_ids(element, &object.name_span, ids)?;
+                        }
+                    },
+                    Some(CachedDefinition::Raw(_)) => self.ids_of_object(object, ids)?,
+                    // Objects without a definition are reported when they are generated
+                    None => ()
+                }
+            }
+        }
+        Ok(())
+    }
 
-        for child in children {
-            match &child.value {
-                StatementValue::Object(object) => {
-                    if let Some(definition) = self.definitions.get(&object.name) {
-                        match definition {
-                            CachedDefinition::Raw(definition) => {
-                                if definition.args.len() != object.arguments.len() {
-                                    return Err((format!("the '{}' definition expects {} args, {} given", object.name, definition.args.len(), object.arguments.len()), child.range.clone()));
-                                }
+    fn ids_of_object(&self, object: &Object, ids: &mut HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        for setter in &object.setters {
+            match (setter.name == "id", &setter.value) {
+                (true, SetterValue::Literal(Literal { value: Value::String(id), span })) => {
+                    Generator::insert_id(ids, id, &object.name, span)?;
+                },
+                (_, SetterValue::Object(value)) => self.object_ids(std::slice::from_ref(value), ids)?,
+                _ => ()
+            }
+        }
+        self.object_ids(&object.children, ids)
+    }
 
-                                let mut inlines: Vec<(String, String)> = Vec::new();
-                                let mut children: Vec<(String, String)> = Vec::new();
-
-                                for i in 0..definition.args.len() {
-                                    let defined_arg = &definition.args[i];
-                                    let actual_arg = &object.arguments[i];
-                                    
-                                    // Check if actual and defined are the same type and if so check if the definition specifies it as an inline or a child
-                                    if let Ok(is_valid) = Generator::is_valid_type(&actual_arg, &defined_arg.1) {
-                                        if is_valid {
-                                            match defined_arg.2 {
-                                                TokenDefinitionType::InlineArg => {
-                                                    inlines.push((defined_arg.0.clone(), actual_arg.value_to_string()));
-                                                },
-                                                TokenDefinitionType::ChildArg => {
-                                                    children.push((defined_arg.0.clone(), actual_arg.value_to_string()));
-                                                },
-                                                _ => return Err((format!("expected either an InlineArg or a ChildArg, got {}", defined_arg.2.to_string()), actual_arg.range.clone()))
-                                            }
-                                        }
-                                    }
-                                }
+    // Menus, and the sections and submenus in them, are GMenus
+    fn menu_ids(menu: &Menu, ids: &mut HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        if let Some(id) = &menu.id {
+            Generator::insert_id(ids, id, "GMenu", &menu.span)?;
+        }
+        for child in &menu.children {
+            Generator::menu_ids(child, ids)?;
+        }
+        Ok(())
+    }
 
-                                for setter in &object.setters {
-                                    let defined_prop = self.get_prop_from_definition(definition, &object.name, &setter);
-                                    let actual_prop = &setter.value;
-
-                                    match defined_prop {
-                                        Ok(defined_prop) => {
-                                            // Check if actual and defined are the same type and if so check if the definition specifies it as an inline or a child
-                                            if let Ok(is_valid) = Generator::is_valid_type(&actual_prop, &defined_prop.0) {
-                                                if is_valid {
-                                                    match defined_prop.1 {
-                                                        TokenDefinitionType::InlineProp => {
-                                                            inlines.push((setter.name.clone(), actual_prop.value_to_string()));
-                                                        },
-                                                        TokenDefinitionType::ChildProp => {
-                                                            children.push((setter.name.clone(), actual_prop.value_to_string()));
-                                                        },
-                                                        _ => return Err((format!("expected either an InlineArg or a ChildArg, got {}", defined_prop.1.to_string()), actual_prop.range.clone()))
-                                                    }
-                                                }
-                                            }
-                                        },
-                                        Err(err) => return Err(err)
-                                    }
-                                }
+    fn element_ids(element: &Element, span: &Span, ids: &mut HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        match (element.attribute("id"), element.attribute("class")) {
+            (Some(id), Some(class)) => Generator::insert_id(ids, id, class, span)?,
+            (Some(id), None) if matches!(element.name.as_str(), "menu" | "section" | "submenu") => Generator::insert_id(ids, id, "GMenu", span)?,
+            _ => ()
+        }
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                Generator::element_ids(child, span, ids)?;
+            }
+        }
+        Ok(())
+    }
 
-                                // Generate from the vectors of inlines and children
+    // GtkBuilder only keeps one object per id, so an id can't be given twice in the same definition
+    fn insert_id(ids: &mut HashMap<String, (String, Span)>, id: &str, class: &str, span: &Span) -> Result<(), Diagnostic> {
+        if let Some((_, first)) = ids.get(id) {
+            return Err(
+                Diagnostic::error(Code::DuplicateId, format!("the id '{}' is given to more than one object", id), span.clone())
+                    .with_primary(span.clone(), "given again here")
+                    .with_label(first.clone(), "first given here")
+            );
+        }
+        ids.insert(id.to_string(), (class.to_string(), span.clone()));
+        Ok(())
+    }
 
-                                result += "<object class=\"";
-                                result += object.name.as_str();
-                                result += "\"";
+    fn unknown_id(code: Code, id: &str, span: &Span, ids: &HashMap<String, (String, Span)>) -> Diagnostic {
+        let diagnostic = Diagnostic::error(code, format!("no object with the id '{}' in this definition", id), span.clone());
+        diagnostic.with_suggestion(id, ids.keys().map(String::as_str))
+    }
 
-                                for inline in &inlines {
-                                    result += " ";
-                                    result += inline.0.as_str();
-                                    result += "=\"";
-                                    result += inline.1.as_str();
-                                    result += "\"";
-                                }
+    // Whether a class is the given class or inherits it through its definitions. Menus don't have a definition, but
+    // are always GMenuModels.
+    fn inherits_class(&self, class: &str, parent: &str) -> bool {
+        class == parent || (class == "GMenu" && parent == "GMenuModel") || match self.definitions.get(class) {
+            Some(CachedDefinition::Raw(definition)) => definition.inherits.iter().any(|inherited| self.inherits_class(inherited, parent)),
+            _ => false
+        }
+    }
 
-                                result += ">\n";
+    // A reference has to point at an object of the same definition whose class is or inherits the class the property
+    // expects
+    fn check_reference(&self, name: &str, literal: &Literal, expected_type: &TokenTypeIdentifierType, declared: &Span, ids: &HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        if let (Value::Reference(id), TokenTypeIdentifierType::Object(required)) = (&literal.value, expected_type) {
+            let class = match ids.get(id) {
+                Some((class, _)) => class,
+                None => return Err(Generator::unknown_id(Code::InvalidReference, id, &literal.span, ids))
+            };
+            if !self.inherits_class(class, required) {
+                return Err(
+                    Diagnostic::error(Code::InvalidReference, format!("'{}' expects a {}, but '{}' is a {}", name, required, id, class), literal.span.clone())
+                        .with_primary(literal.span.clone(), format!("'{}' doesn't inherit {}", class, required))
+                        .with_label(declared.clone(), format!("'{}' is declared here", name))
+                );
+            }
+        }
+        Ok(())
+    }
 
-                                for child in &children {
-                                    result += "<property name=\"";
-                                    result += child.0.as_str();
-                                    result += "\">";
-                                    result += child.1.as_str();
-                                    result += "</property>\n";
-                                }
+    // An object given to a setter has to be of the class the property expects, and it can't be written as an attribute
+    fn check_object_value(&self, name: &str, statement: &Statement, declared: &(TokenTypeIdentifierType, TokenDefinitionType, Span)) -> Result<(), Diagnostic> {
+        if let StatementValue::Object(object) = &statement.value {
+            match &declared.0 {
+                TokenTypeIdentifierType::Object(required) if self.inherits_class(&object.name, required) => (),
+                TokenTypeIdentifierType::Object(required) => return Err(
+                    Diagnostic::error(Code::TypeMismatch, format!("'{}' expects a {}, found a {}", name, required, object.name), object.name_span.clone())
+                        .with_primary(object.name_span.clone(), format!("'{}' doesn't inherit {}", object.name, required))
+                        .with_label(declared.2.clone(), format!("'{}' is declared here", name))
+                ),
+                expected_type => return Err(
+                    Diagnostic::error(Code::TypeMismatch, format!("'{}' expects {}, found object", name, expected_type.to_string()), statement.span.clone())
+                        .with_primary(statement.span.clone(), format!("expected {}", expected_type.to_string()))
+                        .with_label(declared.2.clone(), format!("'{}' is declared here", name))
+                )
+            }
+        }
 
-                                for child in &object.children {
-                                    let child = vec![child.clone()];
-                                    match self.generate_from_collective(&child) {
-                                        Ok(collective) => {
-                                            result += "<child>\n";
-                                            result += collective.as_str();
-                                            result += "</child>\n";
-                                        },
-                                        Err(err) => return Err(err)
-                                    }
+        if let TokenDefinitionType::InlineProp = declared.1 {
+            return Err(
+                Diagnostic::error(Code::InvalidInlineValue, format!("'{}' is an inline value and cannot be an object", name), statement.span.clone())
+                    .with_note("inline values are written as XML attributes, which can't hold an object")
+            );
+        }
+        Ok(())
+    }
+
+    // Numbers of any type can be bound to each other, GObject converts between them
+    fn types_match(a: &TokenTypeIdentifierType, b: &TokenTypeIdentifierType) -> bool {
+        use TokenTypeIdentifierType::{Number, Int, Double};
+        a == b || matches!((a, b), (Number | Int | Double, Number | Int | Double))
+    }
+
+    // The source has to be an object of the same definition. When the bound property is declared on it, the types have
+    // to match.
+    fn check_binding(&self, name: &str, declared: &(TokenTypeIdentifierType, TokenDefinitionType, Span), binding: &Binding, ids: &HashMap<String, (String, Span)>) -> Result<(), Diagnostic> {
+        let class = match ids.get(&binding.source) {
+            Some((class, _)) => class,
+            None => return Err(Generator::unknown_id(Code::InvalidBinding, &binding.source, &binding.source_span, ids))
+        };
+
+        if let TokenDefinitionType::InlineProp = declared.1 {
+            return Err(
+                Diagnostic::error(Code::InvalidBinding, format!("'{}' is an inline value and cannot be bound", name), binding.span.clone())
+                    .with_note("inline values are written as XML attributes, which can't be bound")
+            );
+        }
+
+        let source = match self.definitions.get(class) {
+            Some(CachedDefinition::Raw(source_definition)) => self.lookup(source_definition, &binding.property, |definition| &definition.props),
+            _ => None
+        };
+        if let Some(source) = source {
+            if !Generator::types_match(&declared.0, &source.0) {
+                return Err(
+                    Diagnostic::error(Code::TypeMismatch, format!("'{}' expects {}, but '{}.{}' is {}", name, declared.0.to_string(), binding.source, binding.property, source.0.to_string()), binding.span.clone())
+                        .with_primary(binding.span.clone(), format!("expected {}", declared.0.to_string()))
+                        .with_label(declared.2.clone(), format!("'{}' is declared here", name))
+                        .with_label(source.2.clone(), format!("'{}' is declared here", binding.property))
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn binding(name: &str, binding: &Binding) -> Element {
+        let mut element = Element::new("property")
+            .with_attribute("name", name)
+            .with_attribute("bind-source", &binding.source)
+            .with_attribute("bind-property", &binding.property);
+        if !binding.flags.is_empty() {
+            element = element.with_attribute("bind-flags", &binding.flags.join("|"));
+        }
+        element
+    }
+
+    // The <object> or <template> of an object, with its properties, signals, style, accessibility and children
+    fn object(&self, definition: &CachedRawDefinition, object: &Object, mut element: Element, ids: &HashMap<String, (String, Span)>) -> Result<Element, Diagnostic> {
+        let mut inlines: Vec<(String, String)> = Vec::new();
+        let mut children: Vec<Element> = Vec::new();
+
+        for (defined_arg, actual_arg) in definition.args.iter().zip(object.arguments.iter()) {
+            // Check if actual and defined are the same type and if so check if the definition specifies it as an inline or a child
+            Generator::check_type(&defined_arg.0, actual_arg, &defined_arg.1, &defined_arg.3)?;
+            self.check_reference(&defined_arg.0, actual_arg, &defined_arg.1, &defined_arg.3, ids)?;
+            match defined_arg.2 {
+                TokenDefinitionType::InlineArg => {
+                    inlines.push(Generator::inline_value(&defined_arg.0, actual_arg, &defined_arg.1)?);
+                },
+                TokenDefinitionType::ChildArg => {
+                    children.push(Generator::property(&defined_arg.0, &actual_arg.value, &defined_arg.1));
+                },
+                _ => return Err(Diagnostic::error(Code::Internal, format!("expected either an InlineArg or a ChildArg, got {}", defined_arg.2.to_string()), actual_arg.span.clone()))
+            }
+        }
+
+        // The elements that hold the items added by element definitions, like <items>
+        let mut containers: Vec<Element> = Vec::new();
+        for setter in &object.setters {
+            if let Some((element, declared)) = self.lookup(definition, &setter.name, |definition| &definition.elements) {
+                let item = self.element(element, declared, setter, ids)?;
+                match containers.iter_mut().find(|container| container.name == element.container) {
+                    Some(container) => container.push(Node::Element(item)),
+                    None => containers.push(Element::new(&element.container).with_child(item))
+                }
+                continue;
+            }
+
+            let defined_prop = self.get_prop_from_definition(definition, &object.name, &setter.name, &setter.name_span)?;
+            let actual_prop = match &setter.value {
+                SetterValue::Literal(literal) => literal,
+                SetterValue::Arguments(arguments) => return Err(Generator::arguments_error(&setter.name, arguments)),
+                SetterValue::Binding(binding) => {
+                    self.check_binding(&setter.name, &defined_prop, binding, ids)?;
+                    children.push(Generator::binding(&setter.name, binding));
+                    continue;
+                },
+                SetterValue::Object(value) => {
+                    self.check_object_value(&setter.name, value, &defined_prop)?;
+                    // An object given to a property isn't a child, so it has nothing to take layout properties from
+                    Generator::check_no_parent(std::slice::from_ref(value))?;
+                    let mut property = Element::new("property").with_attribute("name", &setter.name);
+                    for element in self.generate_from_collective(std::slice::from_ref(value), ids)? {
+                        property = property.with_child(element);
+                    }
+                    children.push(property);
+                    continue;
+                }
+            };
+
+            // Check if actual and defined are the same type and if so check if the definition specifies it as an inline or a child
+            Generator::check_type(&setter.name, actual_prop, &defined_prop.0, &defined_prop.2)?;
+            self.check_reference(&setter.name, actual_prop, &defined_prop.0, &defined_prop.2, ids)?;
+            match defined_prop.1 {
+                TokenDefinitionType::InlineProp => {
+                    inlines.push(Generator::inline_value(&setter.name, actual_prop, &defined_prop.0)?);
+                },
+                TokenDefinitionType::ChildProp => {
+                    children.push(Generator::property(&setter.name, &actual_prop.value, &defined_prop.0));
+                },
+                _ => return Err(Diagnostic::error(Code::Internal, format!("expected either an InlineArg or a ChildArg, got {}", defined_prop.1.to_string()), actual_prop.span.clone()))
+            }
+        }
+
+        children.extend(containers);
+
+        for signal in &object.signals {
+            self.check_signal(definition, &object.name, signal, ids)?;
+        }
+
+        let (role, accessibility) = self.accessibility(definition, &object.name, object, ids)?;
+        children.extend(role);
+
+        // Generate from the vectors of inlines and children
+
+        for inline in &inlines {
+            element = element.with_attribute(&inline.0, &inline.1);
+        }
+
+        for child in children {
+            element = element.with_child(child);
+        }
+
+        for signal in &object.signals {
+            element = element.with_child(Generator::signal(signal));
+        }
+
+        if let Some(style) = self.style(object)? {
+            element = element.with_child(style);
+        }
+
+        if let Some(accessibility) = accessibility {
+            element = element.with_child(accessibility);
+        }
+
+        for child in &object.children {
+            let (child_type, layout) = match &child.value {
+                StatementValue::Object(child_object) => (child_object.child_type.as_ref(), self.layout(definition, &object.name, child_object, ids)?),
+                _ => (None, None)
+            };
+            if let Some(child_type) = child_type {
+                self.check_child_type(definition, &object.name, child_type)?;
+            }
+            for mut collective in self.generate_from_collective(std::slice::from_ref(child), ids)? {
+                if let Some(layout) = &layout {
+                    collective.push(Node::Element(layout.clone()));
+                }
+                element = element.with_child(Generator::child(child_type, collective));
+            }
+        }
+        Ok(element)
+    }
+
+    pub fn generate_from_collective(&self, children: &[Statement], ids: &HashMap<String, (String, Span)>) -> Result<Vec<Element>, Diagnostic> {
+        let mut result = Vec::new();
+
+        for child in children {
+            match &child.value {
+                StatementValue::Object(object) => {
+                    if let Some(definition) = self.definitions.get(&object.name) {
+                        match definition {
+                            CachedDefinition::Raw(definition) => {
+                                if definition.args.len() != object.arguments.len() {
+                                    let span = object.arguments_span.clone().unwrap_or(object.name_span.clone());
+                                    return Err(
+                                        Diagnostic::error(Code::ArgumentCount, format!("the '{}' definition expects {} args, {} given", object.name, definition.args.len(), object.arguments.len()), span)
+                                            .with_label(definition.span.clone(), format!("'{}' is defined here", object.name))
+                                    );
                                 }
-                                result += "</object>\n"
+
+                                let element = Element::new("object").with_attribute("class", &object.name);
+                                result.push(self.object(definition, object, element, ids)?);
                             },
-                            CachedDefinition::Collective(defintion) => {
-                                result += defintion.as_str();
+                            CachedDefinition::Collective(definition) => {
+                                result.extend(definition.iter().cloned());
                             }
                         }
-
+                    } else {
+                        let diagnostic = Diagnostic::error(Code::UnknownDefinition, format!("no definition called '{}'", object.name), object.name_span.clone());
+                        return Err(diagnostic.with_suggestion(&object.name, self.definitions.keys().map(String::as_str)));
                     }
                 },
-                _ => return Err((format!("found {}, expected object in collective definition", child.to_string()), child.range.clone()))
+                StatementValue::Menu(menu) => result.push(Generator::menu(menu)),
+                _ => return Err(Diagnostic::error(Code::InvalidChild, format!("found {}, expected object in collective definition", child.to_string()), child.span.clone()))
             }
         }
         
@@ -188,9 +777,15 @@ impl Generator {
         Ok(result)
     }
 
-    pub fn generate_from_raw(&self, definition: &Definition, range: Range<usize>) -> Result<CachedRawDefinition, (String, Range<usize>)> {
-        let mut props: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType)> = HashMap::new();
-        let mut args: Vec<(String, TokenTypeIdentifierType, TokenDefinitionType)> = Vec::new();
+    pub fn generate_from_raw(&self, definition: &Definition) -> Result<CachedRawDefinition, Diagnostic> {
+        let mut props: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType, Span)> = HashMap::new();
+        let mut args: Vec<(String, TokenTypeIdentifierType, TokenDefinitionType, Span)> = Vec::new();
+        let mut layout_props: HashMap<String, (TokenTypeIdentifierType, Span)> = HashMap::new();
+        let mut accessible: HashMap<String, (TokenTypeIdentifierType, TokenDefinitionType, Span)> = HashMap::new();
+        let mut roles: HashMap<String, Span> = HashMap::new();
+        let mut elements: HashMap<String, (ElementDefinition, Span)> = HashMap::new();
+        let mut signals: HashMap<String, Span> = HashMap::new();
+        let mut child_types: HashMap<String, Span> = HashMap::new();
 
         let properties = &definition.children;
         let inherits = &definition.inherits;
@@ -199,56 +794,119 @@ impl Generator {
             if let StatementValue::Property(property_value) = &property.value {
                 match property_value.definition_type {
                     TokenDefinitionType::InlineProp | TokenDefinitionType::ChildProp => {
-                        props.insert(property_value.name.clone(), (property_value.internal_type.clone(), property_value.definition_type.clone()));
+                        props.insert(property_value.name.clone(), (property_value.internal_type.clone(), property_value.definition_type.clone(), property.span.clone()));
                     },
                     TokenDefinitionType::InlineArg | TokenDefinitionType::ChildArg => {
-                        args.push((property_value.name.clone(), property_value.internal_type.clone(), property_value.definition_type.clone()));
+                        args.push((property_value.name.clone(), property_value.internal_type.clone(), property_value.definition_type.clone(), property.span.clone()));
                     },
-                    _ => return Err((format!("expected a property definition, found {}", property_value.definition_type.to_string()), property.range.clone()))
+                    TokenDefinitionType::LayoutProp => {
+                        layout_props.insert(property_value.name.clone(), (property_value.internal_type.clone(), property.span.clone()));
+                    },
+                    TokenDefinitionType::AccessibleProp | TokenDefinitionType::AccessibleRelation | TokenDefinitionType::AccessibleState => {
+                        accessible.insert(property_value.name.clone(), (property_value.internal_type.clone(), property_value.definition_type.clone(), property.span.clone()));
+                    },
+                    _ => return Err(Diagnostic::error(Code::Internal, format!("expected a property definition, found {}", property_value.definition_type.to_string()), property.span.clone()))
                 }
+            } else if let StatementValue::SignalDefinition(signal) = &property.value {
+                signals.insert(signal.name.clone(), property.span.clone());
+            } else if let StatementValue::ChildTypeDefinition(child_type) = &property.value {
+                child_types.insert(child_type.name.clone(), property.span.clone());
+            } else if let StatementValue::RoleDefinition(role) = &property.value {
+                roles.insert(role.name.clone(), property.span.clone());
+            } else if let StatementValue::ElementDefinition(element) = &property.value {
+                elements.insert(element.name.clone(), (element.clone(), property.span.clone()));
             }
         }
 
-        for parent_name in inherits {
+        for (parent_name, parent_span) in inherits {
             if let Some(parent) = self.definitions.get(parent_name) {
                 if let CachedDefinition::Collective(_) = parent {
-                    return Err((format!("cannot inherit collective definition '{}'", parent_name), range))
+                    return Err(Diagnostic::error(Code::InvalidInheritance, format!("cannot inherit collective definition '{}'", parent_name), parent_span.clone()))
                 }
             } else {
-                return Err((format!("'{}' cannot inherit undefined definition '{}'", definition.name, parent_name), range));
+                return Err(Diagnostic::error(Code::InvalidInheritance, format!("'{}' cannot inherit undefined definition '{}'", definition.name, parent_name), parent_span.clone()));
             }
         }
 
         Ok(CachedRawDefinition {
-            inherits: inherits.clone(),
-            range, props, args
+            inherits: inherits.iter().map(|(parent_name, _)| parent_name.clone()).collect(),
+            span: definition.name_span.clone(),
+            props, args, layout_props, accessible, roles, elements, signals, child_types
         })
     }
     
+    // Every root and template is written to its own document, so two of them can't share a name
+    fn check_document(document_spans: &mut HashMap<String, Span>, name: &str, span: &Span) -> Result<(), Diagnostic> {
+        if let Some(first) = document_spans.get(name) {
+            return Err(
+                Diagnostic::error(Code::DuplicateDocument, format!("the document '{}' is generated more than once", name), span.clone())
+                    .with_label(first.clone(), "first generated here")
+                    .with_note("roots are named after the file they are in, templates after their class")
+            );
+        }
+        document_spans.insert(name.to_string(), span.clone());
+        Ok(())
+    }
+
     // Pubs
-    pub fn generate(&mut self) -> Result<(), (String, Range<usize>)> {
+    pub fn generate(&mut self) -> Result<(), Diagnostic> {
+        let mut document_spans: HashMap<String, Span> = HashMap::new();
         for statement in &self.statements {
             match &statement.value {
                 StatementValue::Definition(definition) => {
                     match &definition.definition_type {
                         DefinitionType::Root(filename) => {
-                            // print!("Writing {}.ui... ", filename);
-                            let mut file_content = String::new();
-                            match self.generate_from_collective(&definition.children) {
+                            Generator::check_document(&mut document_spans, filename, &definition.name_span)?;
+                            let mut interface = Element::new("interface");
+                            for node in &self.header {
+                                interface.push(node.clone());
+                            }
+                            Generator::check_no_parent(&definition.children)?;
+                            let mut ids = HashMap::new();
+                            self.object_ids(&definition.children, &mut ids)?;
+                            match self.generate_from_collective(&definition.children, &ids) {
                                 Ok(collective) => {
-                                    file_content.push_str(collective.as_str());
+                                    for object in collective {
+                                        interface.push(Node::Element(object));
+                                    }
                                 },
                                 Err(err) => return Err(err)
                             }
-                            file_content.push_str("</interface>");
 
-                            let mut file = File::create(format!("{}.ui", filename)).expect("failed to create output file");
-                            writeln!(file, "{}", self.header).expect("failed to write to output file");
-                            writeln!(file, "{}", file_content).expect("failed to write to output file");
-                            // println!("done!");
+                            self.documents.insert(filename.clone(), interface.to_document(&self.format));
+                        },
+                        DefinitionType::Template(template) => {
+                            Generator::check_document(&mut document_spans, &template.class, &template.class_span)?;
+                            let parent = match self.definitions.get(&template.parent) {
+                                Some(CachedDefinition::Raw(parent)) => parent,
+                                Some(CachedDefinition::Collective(_)) => return Err(Diagnostic::error(Code::InvalidInheritance, format!("cannot inherit collective definition '{}'", template.parent), template.object.name_span.clone())),
+                                None => return Err(Diagnostic::error(Code::InvalidInheritance, format!("'{}' cannot inherit undefined definition '{}'", template.class, template.parent), template.object.name_span.clone()))
+                            };
+                            // Like the objects of a root, a template has no parent to take layout properties from
+                            if let Some(setter) = template.object.layout.first() {
+                                return Err(Diagnostic::error(Code::UnknownProperty, format!("the template '{}' is not a child of an object, so it has no layout properties", template.class), setter.span.clone()));
+                            }
+
+                            let mut interface = Element::new("interface");
+                            for node in &self.header {
+                                interface.push(node.clone());
+                            }
+                            // GtkBuilder exposes the template under the name of its class
+                            let mut ids = HashMap::new();
+                            ids.insert(template.class.clone(), (template.parent.clone(), template.class_span.clone()));
+                            self.ids_of_object(&template.object, &mut ids)?;
+                            let element = Element::new("template")
+                                .with_attribute("class", &template.class)
+                                .with_attribute("parent", &template.parent);
+                            interface.push(Node::Element(self.object(parent, &template.object, element, &ids)?));
+
+                            self.documents.insert(template.class.clone(), interface.to_document(&self.format));
                         },
                         DefinitionType::Collective => {
-                            match self.generate_from_collective(&definition.children) {
+                            Generator::check_no_parent(&definition.children)?;
+                            let mut ids = HashMap::new();
+                            self.object_ids(&definition.children, &mut ids)?;
+                            match self.generate_from_collective(&definition.children, &ids) {
                                 Ok(collective) => {
                                     self.definitions.insert(definition.name.clone(), CachedDefinition::Collective(collective));
                                 },
@@ -256,9 +914,9 @@ impl Generator {
                             }
                         },
                         DefinitionType::Raw => {
-                            match self.generate_from_raw(&definition, statement.range.clone()) {
+                            match self.generate_from_raw(definition) {
                                 Ok(raw) => {
-                                    self.definitions.insert(definition.name.clone(), CachedDefinition::Raw(raw));
+                                    self.definitions.insert(definition.name.clone(), CachedDefinition::Raw(Box::new(raw)));
                                 },
                                 Err(err) => return Err(err)
                             }
@@ -266,20 +924,26 @@ impl Generator {
                     }
                 },
                 StatementValue::Header(header) => {
-                    self.header.push_str(header);
-                    self.header.push('\n');
+                    self.header.push(Node::Raw(header.clone()));
+                },
+                StatementValue::Requires(library, version) => {
+                    self.header.push(Node::Element(
+                        Element::new("requires").with_attribute("lib", library).with_attribute("version", version)
+                    ));
                 },
-                _ => return Err((format!("this should never ever ever ever ever happen. something must be wrong with the parser if this does happen"), statement.range.clone()))
+                _ => return Err(Diagnostic::error(Code::Internal, "this should never ever ever ever ever happen. something must be wrong with the parser if this does happen", statement.span.clone()))
             }
         }
         Ok(())
     }
 
-    pub fn new(statements: Vec<Statement>) -> Self {
+    pub fn new(statements: Vec<Statement>, format: Format) -> Self {
         Generator {
             statements,
             definitions: HashMap::new(),
-            header: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<interface>\n")
+            header: Vec::new(),
+            format,
+            documents: HashMap::new()
         }
     }
 }
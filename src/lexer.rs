@@ -6,6 +6,9 @@ use super::{
     name_range,
     start_name_range
 };
+use super::util::closest_match;
+use super::diagnostic::{Diagnostic, Code};
+use super::source::{FileId, Span};
 
 // Tokens
 
@@ -15,19 +18,69 @@ pub enum DefinitionType {
     InlineArg,
     ChildProp,
     ChildArg,
+    LayoutProp,
+    AccessibleProp,
+    AccessibleRelation,
+    AccessibleState,
+    AccessibleRole,
+    Element,
+    Signal,
+    ChildType,
     Object(String)
 }
 
 #[derive(Debug, Clone)]
 pub enum DirectiveType {
     Include,
-    Header
+    Header,
+    Requires
 }
+
+// The arguments a directive takes. A single argument is given as is (`#include "gtk-4.0"`), several arguments are given
+// in an argument list (`#requires("gtk", "4.0")`).
+#[derive(Debug, Clone)]
+pub enum DirectiveArguments {
+    None,
+    One(TypeIdentifierType),
+    Many(&'static [TypeIdentifierType])
+}
+
 #[derive(Debug, Clone)]
+pub struct Directive {
+    pub name: &'static str,
+    pub directive_type: DirectiveType,
+    pub arguments: DirectiveArguments
+}
+
+// Every directive known to the lexer and parser. Adding a directive means adding it here and turning its arguments into
+// a statement in `Parser::lower_directive`.
+pub const DIRECTIVES: &[Directive] = &[
+    Directive {
+        name: "include",
+        directive_type: DirectiveType::Include,
+        arguments: DirectiveArguments::One(TypeIdentifierType::String)
+    },
+    Directive {
+        name: "header",
+        directive_type: DirectiveType::Header,
+        arguments: DirectiveArguments::One(TypeIdentifierType::String)
+    },
+    Directive {
+        name: "requires",
+        directive_type: DirectiveType::Requires,
+        arguments: DirectiveArguments::Many(&[TypeIdentifierType::String, TypeIdentifierType::String])
+    }
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeIdentifierType {
     String,
     Number,
-    Bool
+    Int,
+    Double,
+    Bool,
+    // A reference to an object whose class is or inherits the given class, written `Object("GtkWidget")`
+    Object(String)
 }
 
 #[derive(Debug, Clone)]
@@ -36,22 +89,37 @@ pub enum IdentifierType {
     Type(TypeIdentifierType)
 }
 
+#[derive(Debug, Clone)]
+pub struct TranslatableString {
+    pub string: String,
+    pub context: Option<String>,
+    pub comments: Option<String>
+}
+
 #[derive(Debug, Clone)]
 pub enum TokenValue {
     String(String),             // "mystring"
-    Number(f32),                // 0123456789
-    Bool(i32),                  // true, false
+    Translatable(TranslatableString), // _("mystring"), C_("context", "mystring")
+    Integer(i64),               // 42, -4, 0xff, 1_000
+    Float(f64),                 // 0.5, -1.5e3
+    Bool(bool),                  // true, false
+    Reference(String),          // &myobject
     Definition(DefinitionType), // @mydefinition
     Directive(DirectiveType),   // #mydirective
     Setter(String),             // .mysetter
     Identifier(IdentifierType), // anything else
-    Comment,                    // //
+    Whitespace,                 // spaces, tabs and newlines, only kept when lexing losslessly
+    Comment,                    // // or /* */
+    DocComment(String),         // ///
     Inherits,                   // ->
     StartBlock,                 // { 
     EndBlock,                   // }
     StartArgList,               // (
     EndArgList,                 // )
     ArgListDeliminator,         // ,
+    Colon,                      // :
+    Error,                      // a literal that failed to lex, the error has already been reported
+    Unknown,                    // any other input that failed to lex, only kept when lexing losslessly
 }
 
 #[derive(Debug, Clone)]
@@ -66,8 +134,11 @@ impl Token {
     pub fn to_string(&self) -> &str {
         match &self.value {
             TokenValue::String(_) => "string",
-            TokenValue::Number(_) => "number",
+            TokenValue::Translatable(_) => "translatable string",
+            TokenValue::Integer(_) => "integer",
+            TokenValue::Float(_) => "float",
             TokenValue::Bool(_) => "boolean",
+            TokenValue::Reference(_) => "object reference",
             TokenValue::Definition(_) => "definition",
             TokenValue::Directive(_) => "directive",
             TokenValue::Setter(_) => "setter",
@@ -77,23 +148,31 @@ impl Token {
             TokenValue::StartArgList => "(",
             TokenValue::EndArgList => ")",
             TokenValue::ArgListDeliminator => ",",
+            TokenValue::Colon => ":",
             TokenValue::Inherits => "->",
-            TokenValue::Comment => "comment"
+            TokenValue::Error => "invalid literal",
+            TokenValue::Unknown => "unrecognized input",
+            TokenValue::Whitespace => "whitespace",
+            TokenValue::Comment => "comment",
+            TokenValue::DocComment(_) => "doc comment"
         }
     }
     
-    pub fn value_to_string(&self) -> String {
-        match &self.value {
-            TokenValue::String(string) => string.to_string(),
-            TokenValue::Number(number) => number.to_string(),
-            TokenValue::Bool(boolean) => boolean.to_string(),
-            _ => todo!("not implemented yet, but not needed yet!")
-        }
+    // Tokens that carry no meaning for the parser, they are only there to make the token stream lossless
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.value, TokenValue::Whitespace | TokenValue::Comment | TokenValue::Unknown)
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self.value,
+            TokenValue::Integer(_) | TokenValue::Float(_) | TokenValue::String(_) | TokenValue::Translatable(_) | TokenValue::Bool(_) | TokenValue::Reference(_) | TokenValue::Error
+        )
     }
 }
 
 impl DefinitionType {
-    pub fn from(definition: &String) -> TokenValue {
+    pub fn from(definition: &str) -> TokenValue {
         TokenValue::Definition(
             if definition == "InlineProp" {
                 DefinitionType::InlineProp
@@ -103,6 +182,22 @@ impl DefinitionType {
                 DefinitionType::ChildProp
             } else if definition == "ChildArg" {
                 DefinitionType::ChildArg
+            } else if definition == "LayoutProp" {
+                DefinitionType::LayoutProp
+            } else if definition == "AccessibleProp" {
+                DefinitionType::AccessibleProp
+            } else if definition == "AccessibleRelation" {
+                DefinitionType::AccessibleRelation
+            } else if definition == "AccessibleState" {
+                DefinitionType::AccessibleState
+            } else if definition == "AccessibleRole" {
+                DefinitionType::AccessibleRole
+            } else if definition == "Element" {
+                DefinitionType::Element
+            } else if definition == "Signal" {
+                DefinitionType::Signal
+            } else if definition == "ChildType" {
+                DefinitionType::ChildType
             } else {
                 DefinitionType::Object(String::from(definition))
             }
@@ -117,42 +212,100 @@ impl DefinitionType {
             DefinitionType::InlineProp => "InlineProp",
             DefinitionType::ChildArg => "ChildArg",
             DefinitionType::ChildProp => "ChildProp",
+            DefinitionType::LayoutProp => "LayoutProp",
+            DefinitionType::AccessibleProp => "AccessibleProp",
+            DefinitionType::AccessibleRelation => "AccessibleRelation",
+            DefinitionType::AccessibleState => "AccessibleState",
+            DefinitionType::AccessibleRole => "AccessibleRole",
+            DefinitionType::Element => "Element",
+            DefinitionType::Signal => "Signal",
+            DefinitionType::ChildType => "ChildType",
             DefinitionType::Object(_) => "Object"
         }
     }
 }
 
+impl TypeIdentifierType {
+    pub fn to_string(&self) -> &str {
+        match self {
+            TypeIdentifierType::String => "String",
+            TypeIdentifierType::Number => "Number",
+            TypeIdentifierType::Int => "Int",
+            TypeIdentifierType::Double => "Double",
+            TypeIdentifierType::Bool => "Bool",
+            TypeIdentifierType::Object(_) => "Object"
+        }
+    }
+}
+
 impl DirectiveType {
-    pub fn from(directive: &String) -> TokenValue {
-        let directive_type: Option<DirectiveType>;
+    pub fn from(directive: &str) -> Option<TokenValue> {
+        DIRECTIVES.iter()
+            .find(|known| known.name == directive)
+            .map(|known| TokenValue::Directive(known.directive_type.clone()))
+    }
 
-        if directive == "include" {
-            directive_type = Some(DirectiveType::Include);
-        } else if directive == "header" {
-            directive_type = Some(DirectiveType::Header);
-        } else {
-            directive_type = None;
-        }
+    pub fn directive(&self) -> &'static Directive {
+        DIRECTIVES.iter()
+            .find(|known| std::mem::discriminant(&known.directive_type) == std::mem::discriminant(self))
+            .expect("every directive type is in DIRECTIVES")
+    }
+}
 
-        match directive_type {
-            Some(t) => TokenValue::Directive(t),
-            None => panic!("invalid directive")
-        }
+// Strips the indentation shared by all lines of a multi-line string. Blank first and last lines (the ones holding the
+// quotes) are dropped, so the string can be written as an indented block.
+fn strip_indentation(string: &str) -> String {
+    let mut lines: Vec<&str> = string.split('\n').collect();
+    if lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
     }
+    if lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let indentation = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    lines.iter()
+        .map(|line| line.get(indentation..).unwrap_or("").trim_end_matches('\r'))
+        .collect::<Vec<&str>>()
+        .join("\n")
 }
 
 // Lexer
 
+type LexingFunction = fn(&mut Lexer) -> Result<Token, Diagnostic>;
+
+// The lexer walks the input once, front to back. `index` is always a byte offset into `input` and it only ever stops on
+// an ASCII byte or at the end of the input, so every token range is a valid byte span of the source.
 pub struct Lexer {
     pub tokens: Vec<Token>,
     index: usize,
     input: String,
+    file: FileId,
+    errors: Vec<Diagnostic>,
+    // A "Translators:" comment, kept until the end of the first line of code after it
+    translator_comment: Option<String>,
+    code_since_translator_comment: bool
 }
 
 impl Lexer {
 
     // Helper Functions
 
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.index).copied()
+    }
+
+    #[inline]
+    fn peek_nth(&self, n: usize) -> Option<u8> {
+        self.input.as_bytes().get(self.index + n).copied()
+    }
+
     #[inline]
     fn move_foward(&mut self) {
         self.index += 1;
@@ -163,148 +316,324 @@ impl Lexer {
         self.index += n;
     }
 
-    // Lexing Functions
-
-    fn definition(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut definition = String::new();
-        let start_position = self.index.clone();
-        self.move_foward();
-        for c in self.input[self.index..].chars() {
-            match c {
-                name_range!() => {
-                    definition.push(c);
-                },
-                _ => break
+    // Moves forward while the predicate holds and returns the start of the skipped span
+    fn move_while(&mut self, predicate: impl Fn(u8) -> bool) -> usize {
+        let start_position = self.index;
+        while let Some(byte) = self.peek() {
+            if !predicate(byte) {
+                break;
             }
+            self.move_foward();
         }
+        start_position
+    }
 
-        self.move_forward_n(definition.len());
+    // The (possibly multi-byte) character at the cursor, for error messages
+    fn current_char(&self) -> Option<char> {
+        self.input[self.index..].chars().next()
+    }
+
+    fn span(&self, range: Range<usize>) -> Span {
+        Span::new(self.file, range)
+    }
+
+    fn name(&mut self) -> &str {
+        let start_position = self.move_while(|byte| matches!(byte, name_range!()));
+        &self.input[start_position..self.index]
+    }
+
+    // Lexing Functions
+
+    fn definition(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        self.move_foward();
+        let value = DefinitionType::from(self.name());
         Ok(Token {
-            value: DefinitionType::from(&definition),
+            value,
             range: (start_position..self.index)
         })
     }
 
-    fn directive(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut directive = String::new();
-        let start_position = self.index.clone();
+    fn directive(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
         self.move_foward();
-        for c in self.input[self.index..].chars() {
-            match c {
-                name_range!() => {
-                    directive.push(c);
-                },
-                _ => break
+        let name = self.name();
+        match DirectiveType::from(name) {
+            Some(value) => Ok(Token {
+                value,
+                range: (start_position..self.index)
+            }),
+            None => {
+                let message = match closest_match(name, DIRECTIVES.iter().map(|known| known.name)) {
+                    Some(suggestion) => format!("unknown directive '#{}', did you mean '#{}'?", name, suggestion),
+                    None => format!("unknown directive '#{}', expected one of {}", name, DIRECTIVES.iter().map(|known| format!("'#{}'", known.name)).collect::<Vec<String>>().join(", "))
+                };
+                // Like an invalid literal, the directive is replaced by an error token so the parser can skip its arguments
+                self.errors.push(Diagnostic::error(Code::UnknownDirective, message, self.span(start_position..self.index)));
+                Ok(Token {
+                    value: TokenValue::Error,
+                    range: (start_position..self.index)
+                })
             }
         }
-
-        self.move_forward_n(directive.len());
-        Ok(Token {
-            value: DirectiveType::from(&directive),
-            range: (start_position..self.index)
-        })
     }
 
-    fn string(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut string = String::new();
-        let start_position = self.index.clone();
+    fn string(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
         self.move_foward();
         loop {
-            let c = self.input.chars().nth(self.index).unwrap();
-            match c {
-                '\\' => {
-                    string.push(c);
-                    string.push(self.input.chars().nth(self.index + 1).unwrap());
-                    self.move_forward_n(2);
+            match self.peek() {
+                Some(b'\\') => {
+                    // The escaped byte is skipped unchecked, the cursor still lands on a character boundary because the
+                    // loop only ever stops on ASCII bytes.
+                    self.move_forward_n(if self.peek_nth(1).is_some() { 2 } else { 1 });
                 },
-                '"' => {
+                Some(b'"') => {
                     self.move_foward();
                     break
                 },
-                '\n' => {
-                    return Err(( String::from("unexpected end of string input"), (self.index..self.index) ));
+                Some(b'\n') | None => {
+                    return Err(Diagnostic::error(Code::UnterminatedString, "unexpected end of string input", self.span(start_position..self.index))
+                        .with_help("strings can't span lines, use a multi-line string (\"\"\"...\"\"\") instead"));
                 },
-                _ => {
-                    self.move_foward();
-                    string.push(c);
-                }
+                Some(_) => self.move_foward()
             }
         }
 
+        match unescape(&self.input[(start_position + 1)..(self.index - 1)]) {
+            Some(string) =>
+                Ok(Token {
+                    value: TokenValue::String(string),
+                    range: (start_position..self.index)
+                }),
+            None => Err(Diagnostic::error(Code::InvalidEscape, "unable to escape string", self.span(start_position..self.index)))
+        }
+    }
+
+    fn multiline_string(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        self.move_forward_n(3);
+        let content_start = self.index;
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.move_forward_n(if self.peek_nth(1).is_some() { 2 } else { 1 }),
+                Some(b'"') if self.input[self.index..].starts_with("\"\"\"") => break,
+                Some(_) => self.move_foward(),
+                None => return Err(Diagnostic::error(Code::UnterminatedString, "unterminated multi-line string", self.span(start_position..content_start)))
+            }
+        }
+
+        let string = strip_indentation(&self.input[content_start..self.index]);
+        self.move_forward_n(3);
+
         match unescape(string.as_str()) {
             Some(string) =>
                 Ok(Token {
                     value: TokenValue::String(string),
                     range: (start_position..self.index)
                 }),
-            None => Err(( String::from("unable to escape string"), (start_position..self.index) ))
+            None => Err(Diagnostic::error(Code::InvalidEscape, "unable to escape string", self.span(start_position..self.index)))
         }
     }
 
-    fn setter(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut setter = String::new();
-        let start_position = self.index.clone();
+    // r"..." or r#"..."#, the content is taken as written and may contain newlines, quotes and backslashes
+    fn raw_string(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
         self.move_foward();
-        for c in self.input[self.index..].chars() {
-            match c {
-                name_range!() => {
-                    setter.push(c);
-                },
-                _ => break
+        let hashes_start = self.move_while(|byte| byte == b'#');
+        let hashes = self.index - hashes_start;
+        if self.peek() != Some(b'"') {
+            return Err(Diagnostic::error(Code::UnterminatedString, "expected '\"' to start the raw string", self.span(start_position..self.index)));
+        }
+        self.move_foward();
+
+        let content_start = self.index;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        match self.input[content_start..].find(&terminator) {
+            Some(length) => {
+                self.move_forward_n(length + terminator.len());
+                Ok(Token {
+                    value: TokenValue::String(self.input[content_start..(content_start + length)].to_string()),
+                    range: (start_position..self.index)
+                })
+            },
+            None => Err(Diagnostic::error(Code::UnterminatedString, "unterminated raw string", self.span(start_position..content_start)))
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<Token, Diagnostic> {
+        match self.peek() {
+            Some(b'"') if self.input[self.index..].starts_with("\"\"\"") => self.multiline_string(),
+            Some(b'"') => self.string(),
+            Some(b'r') if matches!(self.peek_nth(1), Some(b'"' | b'#')) => self.raw_string(),
+            _ => Err(Diagnostic::error(Code::InvalidTranslatable, "expected a string", self.span(self.index..self.index)))
+        }
+    }
+
+    // The text of a string literal that is part of a translatable string
+    fn string_value(&mut self) -> Result<String, Diagnostic> {
+        match self.string_literal()?.value {
+            TokenValue::String(string) => Ok(string),
+            _ => unreachable!("string literals lex to strings")
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.move_while(|byte| matches!(byte, b' ' | b'\t' | b'\r' | b'\n'));
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), Diagnostic> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.move_foward();
+            Ok(())
+        } else {
+            Err(Diagnostic::error(Code::InvalidTranslatable, format!("expected '{}'", expected as char), self.span(self.index..self.index)))
+        }
+    }
+
+    fn is_translatable(&self) -> bool {
+        self.input[self.index..].starts_with("_(") || self.input[self.index..].starts_with("C_(")
+    }
+
+    // _("mystring") or C_("context", "mystring")
+    fn translatable(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        let has_context = self.name() == "C_";
+        self.translatable_arguments(start_position, has_context).inspect_err(|_| {
+            // Skip the rest of the arguments so they aren't lexed as tokens of their own
+            self.move_while(|byte| byte != b')' && byte != b'\n');
+            if self.peek() == Some(b')') {
+                self.move_foward();
             }
+        })
+    }
+
+    fn translatable_arguments(&mut self, start_position: usize, has_context: bool) -> Result<Token, Diagnostic> {
+        self.expect(b'(')?;
+        self.skip_whitespace();
+        let mut string = self.string_value()?;
+        let mut context = None;
+        if has_context {
+            self.expect(b',')?;
+            self.skip_whitespace();
+            context = Some(string);
+            string = self.string_value()?;
         }
+        self.expect(b')')?;
 
-        self.move_forward_n(setter.len());
         Ok(Token {
-            value: TokenValue::Setter(setter),
+            value: TokenValue::Translatable(TranslatableString {
+                string,
+                context,
+                comments: self.translator_comment.clone()
+            }),
             range: (start_position..self.index)
         })
     }
 
-    fn number(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut number = String::new();
-        let start_position = self.index.clone();
-        for c in self.input[self.index..].chars() {
-            if ! c.is_digit(10) && c != '.' {
-                break
-            }
-            number.push(c);
+    fn reference(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        self.move_foward();
+        let id = self.name().to_string();
+        if id.is_empty() {
+            return Err(Diagnostic::error(Code::UnrecognizedCharacter, "expected the id of an object after '&'", self.span(start_position..self.index)));
         }
+        Ok(Token {
+            value: TokenValue::Reference(id),
+            range: (start_position..self.index)
+        })
+    }
+
+    fn setter(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        self.move_foward();
+        let value = TokenValue::Setter(self.name().to_string());
+        Ok(Token {
+            value,
+            range: (start_position..self.index)
+        })
+    }
+
+    fn digits(&mut self, predicate: impl Fn(u8) -> bool) -> String {
+        let start_position = self.move_while(|byte| predicate(byte) || byte == b'_');
+        self.input[start_position..self.index].replace('_', "")
+    }
+
+    fn number(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        let sign = if self.peek() == Some(b'-') {
+            self.move_foward();
+            "-"
+        } else {
+            ""
+        };
+
+        let mut is_float = false;
+        let value = if self.peek() == Some(b'0') && matches!(self.peek_nth(1), Some(b'x' | b'X')) {
+            self.move_forward_n(2);
+            let digits = self.digits(|byte| byte.is_ascii_hexdigit());
+            if digits.is_empty() {
+                return Err(Diagnostic::error(Code::InvalidNumber, "expected hexadecimal digits after '0x'", self.span(start_position..self.index)));
+            }
+            i64::from_str_radix(&format!("{sign}{digits}"), 16).ok().map(TokenValue::Integer)
+        } else {
+            let mut number = format!("{sign}{}", self.digits(|byte| byte.is_ascii_digit()));
+
+            // A '.' only continues the number if a digit follows, this keeps `1.setter` style input unambiguous
+            if self.peek() == Some(b'.') && self.peek_nth(1).is_some_and(|byte| byte.is_ascii_digit()) {
+                self.move_foward();
+                number.push('.');
+                number += &self.digits(|byte| byte.is_ascii_digit());
+                is_float = true;
+            }
+
+            if matches!(self.peek(), Some(b'e' | b'E')) {
+                let sign_length = usize::from(matches!(self.peek_nth(1), Some(b'+' | b'-')));
+                if self.peek_nth(1 + sign_length).is_some_and(|byte| byte.is_ascii_digit()) {
+                    number.push('e');
+                    number += &self.input[(self.index + 1)..(self.index + 1 + sign_length)];
+                    self.move_forward_n(1 + sign_length);
+                    number += &self.digits(|byte| byte.is_ascii_digit());
+                    is_float = true;
+                }
+            }
 
-        self.move_forward_n(number.len());
+            if is_float {
+                number.parse::<f64>().ok().filter(|float| float.is_finite()).map(TokenValue::Float)
+            } else {
+                number.parse::<i64>().ok().map(TokenValue::Integer)
+            }
+        };
 
-        match number.parse::<f32>() {
-            Ok(num) =>
+        if let Some(byte @ name_range!()) = self.peek() {
+            let suffix_start = self.move_while(|byte| matches!(byte, name_range!()));
+            return Err(Diagnostic::error(Code::InvalidNumber, format!("unexpected character '{}' in number literal", byte as char), self.span(suffix_start..self.index)));
+        }
+
+        match value {
+            Some(value) =>
                 Ok(Token {
-                    value: TokenValue::Number(num),
+                    value,
                     range: (start_position..self.index)
                 }),
-            Err(e) => Err((e.to_string(), (start_position..self.index)))
+            None if is_float => Err(Diagnostic::error(Code::InvalidNumber, "float literal is out of range for a 64-bit float", self.span(start_position..self.index))),
+            None => Err(Diagnostic::error(Code::InvalidNumber, format!("integer literal is out of range, it must be between {} and {}", i64::MIN, i64::MAX), self.span(start_position..self.index)))
         }
     }
 
     // TODO: Rename this function to include its use with parsing booleans
-    fn identifier(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let mut identifier = String::new();
-        let start_position = self.index.clone();
-        for c in self.input[self.index..].chars() {
-            match c {
-                name_range!() => {
-                    identifier.push(c);
-                },
-                _ => break
-            }
-        }
-
-        self.move_forward_n(identifier.len());
-
-        let value = match identifier.as_str() {
-            "true"   => TokenValue::Bool(1),
-            "false"  => TokenValue::Bool(0),
-            "String" => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::String)),
-            "Number" => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Number)),
-            "Bool"   => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Bool)),
-            _        => TokenValue::Identifier(IdentifierType::Generic(identifier))
+    fn identifier(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        let value = match self.name() {
+            "true"     => TokenValue::Bool(true),
+            "false"    => TokenValue::Bool(false),
+            "String"   => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::String)),
+            "Number"   => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Number)),
+            "Int"      => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Int)),
+            "Double"   => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Double)),
+            "Bool"     => TokenValue::Identifier(IdentifierType::Type(TypeIdentifierType::Bool)),
+            identifier => TokenValue::Identifier(IdentifierType::Generic(identifier.to_string()))
         };
 
         Ok(Token {
@@ -313,7 +642,7 @@ impl Lexer {
         })
     }
 
-    fn add_and_move(&mut self, value: TokenValue) -> Result<Token, (String, Range<usize>)> {
+    fn add_and_move(&mut self, value: TokenValue) -> Result<Token, Diagnostic> {
         self.move_foward();
         Ok(Token {
             value,
@@ -321,89 +650,271 @@ impl Lexer {
         })
     }
 
-    fn comment(&mut self) -> Result<Token, (String, Range<usize>)> {
-        let start_position = self.index.clone();
+    fn comment(&mut self) -> Result<Token, Diagnostic> {
+        match self.peek_nth(1) {
+            Some(b'/') => self.line_comment(),
+            Some(b'*') => self.block_comment(),
+            _ => Err(Diagnostic::error(Code::UnrecognizedCharacter, "unrecognized character '/'", self.span(self.index..(self.index + 1)))
+                .with_help("comments start with '//' or '/*'"))
+        }
+    }
+
+    fn line_comment(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.move_while(|byte| byte != b'\n');
+        let comment = &self.input[start_position..self.index];
+
+        // `///` starts a doc comment, but `////` is still just a comment
+        let value = if comment.starts_with("///") && !comment.starts_with("////") {
+            let doc = &comment[3..];
+            TokenValue::DocComment(doc.strip_prefix(' ').unwrap_or(doc).trim_end().to_string())
+        } else {
+            TokenValue::Comment
+        };
+
+        Ok(Token {
+            value,
+            range: (start_position..self.index)
+        })
+    }
+
+    fn block_comment(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        let mut depth = 0;
         loop {
-            let c = self.input.chars().nth(self.index);
-            if let Some(c) = c {
-                if c == '\n' {
-                    break;
-                }
-                self.move_foward();
-            } else {
-                break;
+            match (self.peek(), self.peek_nth(1)) {
+                (Some(b'/'), Some(b'*')) => {
+                    depth += 1;
+                    self.move_forward_n(2);
+                },
+                (Some(b'*'), Some(b'/')) => {
+                    depth -= 1;
+                    self.move_forward_n(2);
+                    if depth == 0 {
+                        break;
+                    }
+                },
+                (Some(_), _) => self.move_foward(),
+                (None, _) => return Err(Diagnostic::error(Code::UnterminatedComment, "unterminated block comment", self.span(start_position..(start_position + 2)))
+                    .with_note("block comments can be nested, every '/*' needs its own '*/'"))
             }
         }
-        return Ok(Token {
+        Ok(Token {
             value: TokenValue::Comment,
             range: (start_position..self.index)
         })
     }
 
-    fn inherits(&mut self) -> Result<Token, (String, Range<usize>)> {
+    // Comments starting with "Translators:" are attached to the translatable strings on the next line of code, like
+    // xgettext does. Comment lines directly following such a comment are part of it.
+    fn remember_translator_comment(&mut self, range: Range<usize>) {
+        let comment = &self.input[range];
+        let comment = comment.strip_prefix("//")
+            .or_else(|| comment.strip_prefix("/*").and_then(|comment| comment.strip_suffix("*/")))
+            .unwrap_or(comment)
+            .trim();
+
+        if let Some(translator_comment) = comment.strip_prefix("Translators:") {
+            self.translator_comment = Some(translator_comment.trim_start().to_string());
+            self.code_since_translator_comment = false;
+        } else if let (Some(translator_comment), false) = (&mut self.translator_comment, self.code_since_translator_comment) {
+            translator_comment.push('\n');
+            translator_comment.push_str(comment);
+        }
+    }
+
+    // A '-' that isn't the start of '->' or of a number is replaced by an error token, so the parser skips it
+    fn inherits(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
         self.move_foward();
-        if let Some(char) = self.input.chars().nth(self.index) {
-            if char == '>' {
+        let err = match self.peek() {
+            Some(b'>') => {
                 self.move_foward();
-                Ok(Token {
+                return Ok(Token {
                     value: TokenValue::Inherits,
-                    range: ((self.index - 2)..self.index)
-                })
-            } else {
-                Err((format!("unrecognized character '{}'", char), (self.index..(self.index + 1))))
-            }
-        } else {
-            Err((format!("unexpected end of input"), (self.index..self.index)))
-        }
+                    range: (start_position..self.index)
+                });
+            },
+            Some(_) => Diagnostic::error(Code::UnrecognizedCharacter, format!("expected '>' after '-', found '{}'", self.current_char().unwrap_or_default()), self.span(start_position..self.index)),
+            None => Diagnostic::error(Code::UnrecognizedCharacter, "unexpected end of input", self.span(self.index..self.index))
+        };
+        self.errors.push(err);
+        Ok(Token {
+            value: TokenValue::Error,
+            range: (start_position..self.index)
+        })
+    }
+
+    // Lexes a literal. If that fails, the error is recorded and an error token takes the literal's place, so the parser
+    // doesn't report a missing value on top of it.
+    fn literal(&mut self, lex: LexingFunction) -> Result<Token, Diagnostic> {
+        let start_position = self.index;
+        lex(self).or_else(|err| {
+            self.errors.push(err);
+            Ok(Token {
+                value: TokenValue::Error,
+                range: (start_position..self.index)
+            })
+        })
+    }
+
+    fn whitespace(&mut self) -> Result<Token, Diagnostic> {
+        let start_position = self.move_while(|byte| matches!(byte, b' ' | b'\t' | b'\r' | b'\n'));
+        Ok(Token {
+            value: TokenValue::Whitespace,
+            range: (start_position..self.index)
+        })
+    }
+
+    fn unrecognized(&mut self) -> Result<Token, Diagnostic> {
+        let c = self.current_char().unwrap_or_default();
+        Err(Diagnostic::error(Code::UnrecognizedCharacter, format!("unrecognized character '{}'", c), self.span(self.index..(self.index + c.len_utf8()))))
     }
 
     // Pubs
-    pub fn new(s: String) -> Self {
+    pub fn new(s: String, file: FileId) -> Self {
         Self {
             tokens: Vec::new(),
             index: 0,
             input: s,
+            file,
+            errors: Vec::new(),
+            translator_comment: None,
+            code_since_translator_comment: false
         }
     }
-    pub fn lex(&mut self, lex_comments: bool) -> Result<(), (String, Range<usize>)> {
-        loop {
-            let input_char = self.input.chars().nth(self.index);
-            if let Some(c) = input_char {
-                let token = match c {
-                    '@'                 => self.definition(),
-                    '#'                 => self.directive(),
-                    '"'                 => self.string(),
-                    '.'                 => self.setter(),
-                    '0'..='9'           => self.number(),
-                    '-'                 => self.inherits(),
-                    start_name_range!() => self.identifier(),
-                    '{'                 => self.add_and_move(TokenValue::StartBlock),
-                    '}'                 => self.add_and_move(TokenValue::EndBlock),
-                    ','                 => self.add_and_move(TokenValue::ArgListDeliminator),
-                    '('                 => self.add_and_move(TokenValue::StartArgList),
-                    ')'                 => self.add_and_move(TokenValue::EndArgList),
-                    ' ' | '\t' | '\n'   => {
-                        self.move_foward();
-                        continue
-                    },
-                    '/'                 => {
-                        let comment = self.comment();
-                        if lex_comments {
-                            comment
-                        } else {
-                            continue
+    // Lexes the whole input. Errors don't stop the lexer, the offending input is skipped and every error is returned at
+    // the end while `tokens` holds everything that could be lexed.
+    //
+    // When lexing losslessly, whitespace, comments and unrecognized input are kept as trivia tokens, so the tokens cover
+    // every byte of the input.
+    pub fn lex(&mut self, lossless: bool) -> Result<(), Vec<Diagnostic>> {
+        while let Some(byte) = self.peek() {
+            let start_position = self.index;
+            let token = match byte {
+                b'@'                 => self.definition(),
+                b'#'                 => self.directive(),
+                b'"'                 => self.literal(Self::string_literal),
+                b'.'                 => self.setter(),
+                b'&'                 => self.literal(Self::reference),
+                b'0'..=b'9'          => self.literal(Self::number),
+                b'-'                 => {
+                    if self.peek_nth(1).is_some_and(|byte| byte.is_ascii_digit()) {
+                        self.literal(Self::number)
+                    } else {
+                        self.inherits()
+                    }
+                },
+                b'r' if matches!(self.peek_nth(1), Some(b'"' | b'#')) => self.literal(Self::string_literal),
+                b'_' | b'C' if self.is_translatable() => self.literal(Self::translatable),
+                start_name_range!()  => self.identifier(),
+                b'{'                 => self.add_and_move(TokenValue::StartBlock),
+                b'}'                 => self.add_and_move(TokenValue::EndBlock),
+                b','                 => self.add_and_move(TokenValue::ArgListDeliminator),
+                b':'                 => self.add_and_move(TokenValue::Colon),
+                b'('                 => self.add_and_move(TokenValue::StartArgList),
+                b')'                 => self.add_and_move(TokenValue::EndArgList),
+                b' ' | b'\t' | b'\r' | b'\n' => self.whitespace(),
+                b'/'                 => self.comment(),
+                _ => self.unrecognized(),
+            };
+
+            match token {
+                Ok(token) => {
+                    match token.value {
+                        TokenValue::Whitespace => {
+                            if self.code_since_translator_comment && self.input[token.range.clone()].contains('\n') {
+                                self.translator_comment = None;
+                            }
+                        },
+                        TokenValue::Comment => self.remember_translator_comment(token.range.clone()),
+                        _ => {
+                            if self.translator_comment.is_some() {
+                                self.code_since_translator_comment = true;
+                            }
                         }
-                    },
-                    _ => Err((format!("unrecognized character '{}'", c), (self.index..self.index))),
-                };
-
-                match token {
-                    Ok(token) => self.tokens.push(token),
-                    Err(err) => return Err(err)
+                    }
+                    if token.is_trivia() && !lossless {
+                        continue;
+                    }
+                    self.tokens.push(token)
+                },
+                Err(err) => {
+                    self.errors.push(err);
+                    if self.index == start_position {
+                        self.move_forward_n(self.current_char().map_or(1, char::len_utf8));
+                    }
+                    if lossless {
+                        self.tokens.push(Token {
+                            value: TokenValue::Unknown,
+                            range: (start_position..self.index)
+                        });
+                    }
                 }
-            } else {
-                return Ok(());
             }
         }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::source::SourceMap;
+
+    fn lex(source: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+        let file = SourceMap::new().add("test.gui".to_string(), source.to_string(), None);
+        let mut lexer = Lexer::new(source.to_string(), file);
+        let errors = lexer.lex(false).err().unwrap_or_default();
+        (lexer.tokens, errors)
+    }
+
+    #[test]
+    fn dash_without_arrow() {
+        let (tokens, errors) = lex("(-)");
+        assert!(matches!(tokens[..], [_, Token { value: TokenValue::Error, .. }, _]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Code::UnrecognizedCharacter);
+    }
+
+    #[test]
+    fn hex_prefix_without_digits() {
+        let (tokens, errors) = lex("0x");
+        assert!(matches!(tokens[..], [Token { value: TokenValue::Error, .. }]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Code::InvalidNumber);
+    }
+
+    #[test]
+    fn exponent_without_digits() {
+        let (tokens, errors) = lex("1e");
+        assert!(matches!(tokens[..], [Token { value: TokenValue::Error, .. }]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unexpected character 'e' in number literal");
+    }
+
+    #[test]
+    fn smallest_integer() {
+        let (tokens, errors) = lex("-9223372036854775808");
+        assert!(errors.is_empty());
+        assert!(matches!(tokens[..], [Token { value: TokenValue::Integer(i64::MIN), .. }]));
+    }
+
+    #[test]
+    fn integer_out_of_range() {
+        let (tokens, errors) = lex("-9223372036854775809");
+        assert!(matches!(tokens[..], [Token { value: TokenValue::Error, .. }]));
+        assert_eq!(errors[0].code, Code::InvalidNumber);
+    }
+
+    #[test]
+    fn float_out_of_range() {
+        let (tokens, errors) = lex("1e400");
+        assert!(matches!(tokens[..], [Token { value: TokenValue::Error, .. }]));
+        assert_eq!(errors[0].message, "float literal is out of range for a 64-bit float");
     }
 }
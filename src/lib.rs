@@ -1,7 +1,14 @@
 
 pub mod lexer;
 pub mod parser;
+pub mod syntax;
 pub mod preprocessor;
 pub mod generator;
 pub mod macros;
 pub mod util;
+pub mod diagnostic;
+pub mod source;
+pub mod compiler;
+pub mod xml;
+
+pub use compiler::{compile, Compiler, Options, Output, FileLoader, FsLoader, MemoryLoader};
@@ -1,4 +1,4 @@
 #[macro_export]
-macro_rules! start_name_range{() => {'a'..='z' | 'A'..='Z' | '_'}}
+macro_rules! start_name_range{() => {b'a'..=b'z' | b'A'..=b'Z' | b'_'}}
 #[macro_export]
-macro_rules! name_range{() => {'a'..='z' | 'A'..='Z' | '-' | '_'}}
+macro_rules! name_range{() => {b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_'}}
@@ -2,14 +2,58 @@ use std::fs;
 use std::env;
 use std::process;
 
-use gtk_ui::lexer::Lexer;
-use gtk_ui::parser::Parser;
-use gtk_ui::preprocessor::Preprocessor;
-use gtk_ui::generator::Generator;
-use gtk_ui::util::check_error;
+use gtk_ui::compiler::{Compiler, Options};
+use gtk_ui::diagnostic::{Diagnostic, Code, CODES};
+use gtk_ui::source::SourceMap;
+use gtk_ui::xml::Format;
 
 fn print_help() {
-    println!("Usage: gtk-ui [FILENAME]");
+    println!("Usage: gtk-ui [OPTIONS] [FILENAME]");
+    println!("       gtk-ui --explain [CODE]");
+    println!();
+    println!("Options:");
+    println!("  --indent [N]  Indent the generated XML by N spaces (default: 2)");
+    println!("  --minify      Write the generated XML without any whitespace");
+}
+
+fn explain(code: &str) {
+    match Code::from(code) {
+        Some(code) => print!("{}", code.explanation()),
+        None => {
+            println!("'{}' is not a valid error code, the valid codes are:", code);
+            for code in CODES {
+                println!("  {}", code.as_str());
+            }
+            process::exit(1);
+        }
+    }
+}
+
+// Prints every diagnostic and exits if any of them is an error
+fn check_errors(mut diagnostics: Vec<Diagnostic>, source_map: &SourceMap) {
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span().map(|span| (span.file, span.range.start)));
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic.render(source_map));
+    }
+
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    if errors > 0 {
+        let mut codes: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.code.as_str()).collect();
+        codes.sort();
+        codes.dedup();
+        if errors == 1 {
+            println!("\x1b[1;31merror\x1b[0m\x1b[1m: aborting due to previous error\x1b[0m");
+        } else {
+            println!("\x1b[1;31merror\x1b[0m\x1b[1m: aborting due to {} previous errors\x1b[0m", errors);
+        }
+        if codes.len() == 1 {
+            println!("For more information about this error, try `gtk-ui --explain {}`.", codes[0]);
+        } else {
+            println!("Some errors have detailed explanations: {}.", codes.join(", "));
+            println!("For more information about an error, try `gtk-ui --explain {}`.", codes[0]);
+        }
+        process::exit(1);
+    }
 }
 
 fn main() {
@@ -20,19 +64,54 @@ fn main() {
         process::exit(0);
     }
 
-    let filename = &args[1];
+    if args[1] == "--explain" {
+        match args.get(2) {
+            Some(code) => explain(code),
+            None => print_help()
+        }
+        process::exit(0);
+    }
+
+    let mut format = Format::default();
+    let mut filename: Option<&String> = None;
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--minify" => format = Format::minified(),
+            "--indent" => {
+                match args.next().and_then(|indent| indent.parse().ok()) {
+                    Some(indent) => format = Format::pretty(indent),
+                    None => {
+                        println!("--indent expects a number of spaces");
+                        process::exit(1);
+                    }
+                }
+            },
+            _ => filename = Some(arg)
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => {
+            print_help();
+            process::exit(1);
+        }
+    };
+
     let file_content = fs::read_to_string(filename)
         .expect("Something went wrong while trying to read the file");
     
-    let mut lexer = Lexer::new(file_content.clone());
-    check_error(lexer.lex(false), filename, &file_content);
-    
-    let mut parser = Parser::new(lexer.tokens, filename.clone());
-    check_error(parser.parse(), filename, &file_content);
-
-    let mut preprocessor = Preprocessor::new();
-    check_error(preprocessor.preprocess(parser.statements, vec![filename.clone()]), filename, &file_content);
-    
-    let mut generator = Generator::new(preprocessor.statements);
-    check_error(generator.generate(), filename, &file_content);
+    let mut compiler = Compiler::new();
+    match compiler.compile(&file_content, &Options::new(filename).with_format(format)) {
+        Ok(output) => {
+            check_errors(output.warnings, &compiler.source_map);
+            for (name, document) in output.documents {
+                if let Err(err) = fs::write(format!("{}.ui", name), document) {
+                    println!("\x1b[1;31merror\x1b[0m\x1b[1m: failed to write {}.ui: {}\x1b[0m", name, err);
+                    process::exit(1);
+                }
+            }
+        },
+        Err(errors) => check_errors(errors, &compiler.source_map)
+    }
 }
@@ -3,442 +3,1694 @@ use super::lexer::{
     TokenValue,
     DefinitionType as TokenDefinitionType,
     DirectiveType as TokenDirectiveType,
+    DirectiveArguments as TokenDirectiveArguments,
     IdentifierType as TokenIdentifierType,
-    TypeIdentifierType as TokenTypeIdentifierType
+    TypeIdentifierType as TokenTypeIdentifierType,
+    TranslatableString
+};
+use super::diagnostic::{Diagnostic, Code};
+use super::source::{FileId, Span};
+use super::syntax::{
+    SyntaxKind,
+    SyntaxNode,
+    SyntaxElement
 };
 use std::path::Path;
 use std::ops::Range;
 
 // Statement
 
+// A literal value, as it is given to an argument or a setter
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    Translatable(TranslatableString),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    // The id of another object
+    Reference(String)
+}
+
+impl Value {
+    // Invalid literals have no value, their error has already been reported
+    pub fn from(token: &Token) -> Option<Value> {
+        match &token.value {
+            TokenValue::String(string) => Some(Value::String(string.clone())),
+            TokenValue::Translatable(translatable) => Some(Value::Translatable(translatable.clone())),
+            TokenValue::Integer(integer) => Some(Value::Integer(*integer)),
+            TokenValue::Float(float) => Some(Value::Float(*float)),
+            TokenValue::Bool(boolean) => Some(Value::Bool(*boolean)),
+            TokenValue::Reference(id) => Some(Value::Reference(id.clone())),
+            _ => None
+        }
+    }
+
+    pub fn to_string(&self) -> &str {
+        match self {
+            Value::String(_) => "string",
+            Value::Translatable(_) => "translatable string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "boolean",
+            Value::Reference(_) => "object reference"
+        }
+    }
+
+    pub fn is_of_type(&self, expected_type: &TokenTypeIdentifierType) -> bool {
+        matches!(
+            (self, expected_type),
+            (Value::String(_) | Value::Translatable(_), TokenTypeIdentifierType::String)
+                | (Value::Integer(_) | Value::Float(_), TokenTypeIdentifierType::Number | TokenTypeIdentifierType::Double)
+                | (Value::Integer(_), TokenTypeIdentifierType::Int)
+                | (Value::Bool(_), TokenTypeIdentifierType::Bool)
+                | (Value::Reference(_), TokenTypeIdentifierType::Object(_))
+        )
+    }
+
+    pub fn value_to_string(&self) -> String {
+        match self {
+            Value::String(string) => string.to_string(),
+            Value::Translatable(translatable) => translatable.string.to_string(),
+            Value::Integer(integer) => integer.to_string(),
+            Value::Float(float) => float.to_string(),
+            Value::Bool(boolean) => boolean.to_string(),
+            Value::Reference(id) => id.to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Literal {
+    pub value: Value,
+    pub span: Span
+}
+
+// The spans of the '{' and '}' around a block
+#[derive(Debug, Clone)]
+pub struct Braces {
+    pub open: Span,
+    pub close: Span
+}
+
 #[derive(Debug, Clone)]
 pub struct Property {
     pub internal_type: TokenTypeIdentifierType,
     pub name: String,
-    pub definition_type: TokenDefinitionType
+    pub definition_type: TokenDefinitionType,
+    pub name_span: Span,
+    pub type_span: Span
+}
+
+// @Signal("clicked"), @ChildType("start") or @AccessibleRole("button"), definitions that only declare a name
+#[derive(Debug, Clone)]
+pub struct NameDefinition {
+    pub name: String,
+    pub name_span: Span
+}
+
+// An attribute of the items an element definition adds, `Optional(String)` attributes can be left out
+#[derive(Debug, Clone)]
+pub struct ElementAttribute {
+    pub name: String,
+    pub value_type: TokenTypeIdentifierType,
+    pub optional: bool
+}
+
+// @Element("item", "items", String, id: Optional(String)), a setter that adds an <item> to the <items> of an object. The
+// positional type is the text of the item and the named ones are its attributes.
+#[derive(Debug, Clone)]
+pub struct ElementDefinition {
+    pub name: String,
+    pub name_span: Span,
+    pub container: String,
+    pub text: Option<TokenTypeIdentifierType>,
+    pub attributes: Vec<ElementAttribute>
 }
 
 #[derive(Debug, Clone)]
 pub enum DefinitionType {
     Raw,
     Collective,
-    Root(String)
+    Root(String),
+    Template(Box<Template>)
+}
+
+// @template("MyWindow", parent: GtkApplicationWindow) { ... } .title("My Window"), a composite widget template. Its body
+// is lowered like an object of the parent class.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub class: String,
+    pub class_span: Span,
+    pub parent: String,
+    pub object: Object
 }
 
 #[derive(Debug, Clone)]
 pub struct Definition {
     pub name: String,
+    pub name_span: Span,
     pub children: Vec<Statement>,
-    pub inherits: Vec<String>,
+    pub inherits: Vec<(String, Span)>,
+    pub block: Braces,
     pub definition_type: DefinitionType
 }
 
+// The flags a binding can be given, as GtkBuilder names them in `bind-flags`
+pub const BINDING_FLAGS: &[&str] = &["default", "bidirectional", "sync-create", "invert-boolean"];
+
+// bind source.property, flags
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub source: String,
+    pub source_span: Span,
+    pub property: String,
+    pub property_span: Span,
+    pub flags: Vec<String>,
+    pub span: Span
+}
+
+// .response(_("_Cancel"), "cancel", enabled: false), only setters of element definitions take more than one argument
+#[derive(Debug, Clone)]
+pub struct Arguments {
+    pub positional: Vec<Literal>,
+    pub named: Vec<(String, Span, Literal)>,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub enum SetterValue {
+    Literal(Literal),
+    Arguments(Arguments),
+    Binding(Binding),
+    // An object constructed in place, always an object statement
+    Object(Box<Statement>)
+}
+
 #[derive(Debug, Clone)]
 pub struct Setter {
     pub name: String,
-    pub value: Token,
-    pub range: Range<usize>
+    pub name_span: Span,
+    pub value: SetterValue,
+    pub span: Span
+}
+
+// .on("clicked", "on_clicked", swapped: true, after: false, object: "window1"), options that aren't given are left out
+// of the output
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub name: String,
+    pub name_span: Span,
+    pub handler: String,
+    pub swapped: Option<bool>,
+    pub after: Option<bool>,
+    pub object: Option<(String, Span)>,
+    pub span: Span
+}
+
+// start: or internal vbox:, how an object is added to its parent
+#[derive(Debug, Clone)]
+pub struct ChildType {
+    pub name: String,
+    pub internal: bool,
+    pub span: Span
 }
 
 #[derive(Debug, Clone)]
 pub struct Object {
     pub name: String,
+    pub name_span: Span,
     pub children: Vec<Statement>,
-    pub arguments: Vec<Token>,
-    pub setters: Vec<Setter>
+    pub block: Option<Braces>,
+    pub arguments: Vec<Literal>,
+    pub arguments_span: Option<Span>,
+    pub setters: Vec<Setter>,
+    pub signals: Vec<Signal>,
+    pub child_type: Option<ChildType>,
+    // .layout.column(1), the layout properties of the object in its parent
+    pub layout: Vec<Setter>,
+    // .accessible.label("Save"), the accessible properties, relations and states of the object, and its role
+    pub accessible: Vec<Setter>,
+    // .class("suggested-action"), the style classes of the object
+    pub classes: Vec<(String, Span)>
+}
+
+// menu, section, submenu and item, the elements of a menu model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuKind {
+    Menu,
+    Section,
+    Submenu,
+    Item
+}
+
+impl MenuKind {
+    fn from(name: &str) -> Option<MenuKind> {
+        match name {
+            "menu" => Some(MenuKind::Menu),
+            "section" => Some(MenuKind::Section),
+            "submenu" => Some(MenuKind::Submenu),
+            "item" => Some(MenuKind::Item),
+            _ => None
+        }
+    }
+
+    pub fn to_string(&self) -> &str {
+        match self {
+            MenuKind::Menu => "menu",
+            MenuKind::Section => "section",
+            MenuKind::Submenu => "submenu",
+            MenuKind::Item => "item"
+        }
+    }
+}
+
+// The attributes sections, submenus and items can be given, as GMenu names them
+pub const MENU_ATTRIBUTES: &[&str] = &["label", "action", "target", "icon", "accel", "hidden-when"];
+
+// menu { section { item(_("_Quit"), "app.quit") } }.id("app-menu"), only `menu` itself is allowed outside of a menu
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub kind: MenuKind,
+    pub id: Option<String>,
+    pub attributes: Vec<(String, Literal)>,
+    pub children: Vec<Menu>,
+    pub span: Span
 }
 
 #[derive(Debug, Clone)]
 pub enum StatementValue {
     Property(Property),
+    SignalDefinition(NameDefinition),
+    ChildTypeDefinition(NameDefinition),
+    RoleDefinition(NameDefinition),
+    ElementDefinition(ElementDefinition),
     Definition(Definition),
     Object(Object),
+    Menu(Menu),
     Header(String),
-    Include(String)
+    Include(String),
+    Requires(String, String)
 }
 
+// `span` covers the whole statement, without its doc comments
 #[derive(Debug, Clone)]
 pub struct Statement {
     pub value: StatementValue,
-    pub range: Range<usize>
+    pub span: Span,
+    pub doc: Option<String>
 }
 
 impl Statement {
     pub fn to_string(&self) -> &str {
         match &self.value {
             StatementValue::Property(_) => "Property",
+            StatementValue::SignalDefinition(_) => "Signal",
+            StatementValue::ChildTypeDefinition(_) => "ChildType",
+            StatementValue::RoleDefinition(_) => "AccessibleRole",
+            StatementValue::ElementDefinition(_) => "Element",
             StatementValue::Definition(_) => "Definition",
             StatementValue::Object(_) => "Object",
+            StatementValue::Menu(_) => "Menu",
             StatementValue::Header(_) => "Header",
-            StatementValue::Include(_) => "Include"
+            StatementValue::Include(_) => "Include",
+            StatementValue::Requires(..) => "Requires"
         }
     }
 }
 
 // Parser
 
+// What an argument list can hold besides plain arguments. Setters can be given a binding, signal handlers and setters
+// take named arguments, templates name their parent class with one and element definitions declare attributes with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgListKind {
+    Plain,
+    Setter,
+    Signal,
+    Template,
+    Element
+}
+
+// Parsing happens in two steps. The tokens are first parsed into a concrete syntax tree (`syntax`), which only checks
+// that the tokens are in an order that makes sense. The statements are then derived from that tree.
 pub struct Parser {
     pub statements: Vec<Statement>,
+    pub syntax: SyntaxNode,
     index: usize,
     tokens: Vec<Token>,
-    filename: String
+    file: FileId,
+    filename: String,
+    errors: Vec<Diagnostic>,
+    // Diagnostics that don't stop the compilation
+    pub warnings: Vec<Diagnostic>,
+    // The nodes that are being built, the innermost one last
+    nodes: Vec<SyntaxNode>
 }
 
 impl Parser {
+    // Helper Functions
+
+    fn span(&self, range: Range<usize>) -> Span {
+        Span::new(self.file, range)
+    }
+
+    fn end_of_input(&self) -> Range<usize> {
+        self.tokens.last().map_or(0..0, |token| token.range.end..token.range.end)
+    }
+
+    fn push_element(&mut self, element: SyntaxElement) {
+        self.nodes.last_mut().expect("the parser always has a node to add to").children.push(element);
+    }
+
+    // Moves the trivia at the cursor into the node that is being built
+    fn skip_trivia(&mut self) {
+        while let Some(token) = self.tokens.get(self.index) {
+            if !token.is_trivia() {
+                break;
+            }
+            let token = token.clone();
+            self.push_element(SyntaxElement::Token(token));
+            self.index += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<Token> {
+        self.skip_trivia();
+        self.tokens.get(self.index).cloned()
+    }
+
+    // The nth token after the one at the cursor, without moving the cursor
+    fn peek_ahead(&self, n: usize) -> Option<Token> {
+        self.tokens.get((self.index + 1)..)?.iter().filter(|token| !token.is_trivia()).nth(n - 1).cloned()
+    }
+
+    fn bump(&mut self) {
+        if let Some(token) = self.peek() {
+            self.push_element(SyntaxElement::Token(token));
+            self.index += 1;
+        }
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        // Trivia in front of a node belongs to its parent
+        self.skip_trivia();
+        self.nodes.push(SyntaxNode::new(kind));
+    }
+
+    fn finish_node(&mut self) {
+        let node = self.nodes.pop().expect("finished a node that was never started");
+        self.push_element(SyntaxElement::Node(node));
+    }
+
+    // Parses a statement. If that fails, the error is recorded and everything the statement consumed, together with
+    // the tokens skipped to get back on track, is put into an error node.
+    fn recovering_statement(&mut self) {
+        self.skip_trivia();
+        let depth = self.nodes.len();
+        let start = self.nodes[depth - 1].children.len();
+        let start_index = self.index;
+
+        // An error token was already reported by the lexer, so it is skipped along with whatever belongs to it without
+        // reporting anything else
+        let failed = match self.peek() {
+            Some(Token { value: TokenValue::Error, .. }) => true,
+            _ => self.statement().map_err(|err| self.errors.push(err)).is_err()
+        };
+        if failed {
+            while self.nodes.len() > depth {
+                self.finish_node();
+            }
+            let children = self.nodes[depth - 1].children.split_off(start);
+            self.nodes.push(SyntaxNode {
+                kind: SyntaxKind::Error,
+                children
+            });
+            self.synchronize(start_index);
+            self.finish_node();
+        }
+    }
+
+    // Skips tokens after an error until the end of the current block or the next definition or directive. Blocks opened
+    // on the way are skipped as a whole. At least one token is skipped, so a statement that failed without consuming
+    // anything can't fail again.
+    fn synchronize(&mut self, start_index: usize) {
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token.value {
+                TokenValue::StartBlock => depth += 1,
+                TokenValue::EndBlock if depth == 0 => break,
+                TokenValue::EndBlock => depth -= 1,
+                TokenValue::Definition(TokenDefinitionType::Object(_)) | TokenValue::Directive(_) | TokenValue::DocComment(_) if depth == 0 => break,
+                _ => ()
+            }
+            self.bump();
+        }
+        if self.index == start_index {
+            self.bump();
+        }
+    }
+
     // Parsing Functions
 
-    fn block(&mut self) -> Result<(Vec<Statement>, Range<usize>), (String, Range<usize>)> {
-        if let Some(token) = self.tokens.get(self.index) {
-            let token_range = token.range.clone();
-            if let TokenValue::StartBlock = token.value {
-                self.index += 1;
-                let mut statements = Vec::new();
-                loop {
-                    if let Some(token) = self.tokens.get(self.index) {
-                        if let TokenValue::EndBlock = token.value {
-                            self.index += 1;
-                            break;
-                        }
-                        match self.parse_statement() {
-                            Some(result) => {
-                                match result {
-                                    Ok(statement) => {
-                                        match &statement.value {
-                                            StatementValue::Property(_) | StatementValue::Object(_) => statements.push(statement),
-                                            _ => return Err((format!("found {} inside block. Only properties and objects are allowed here.", statement.to_string()), statement.range)),
-                                        }
-                                    },
-                                    Err(err) => return Err(err)
-                                }
-                            }
-                            None => continue
-                        }
-                    }
+    fn block(&mut self) -> Result<(), Diagnostic> {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected the start of a block, found nothing", self.span(self.end_of_input())))
+        };
+        if !matches!(token.value, TokenValue::StartBlock) {
+            return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected the start of a block, found {}", token.to_string()), self.span(token.range)));
+        }
+
+        self.start_node(SyntaxKind::Block);
+        self.bump();
+        loop {
+            match self.peek() {
+                Some(Token { value: TokenValue::EndBlock, .. }) => {
+                    self.bump();
+                    break;
+                },
+                // A definition or directive can't be inside a block, so the block was most likely not closed
+                Some(next @ Token { value: TokenValue::Definition(TokenDefinitionType::Object(_)) | TokenValue::Directive(_), .. }) => {
+                    self.errors.push(
+                        Diagnostic::error(Code::UnclosedBlock, format!("expected '}}', found {}", next.to_string()), self.span(next.range))
+                            .with_label(self.span(token.range.clone()), "block opened here")
+                    );
+                    break;
+                },
+                Some(_) => self.recovering_statement(),
+                None => {
+                    self.errors.push(
+                        Diagnostic::error(Code::UnclosedBlock, "expected '}', found nothing", self.span(self.end_of_input()))
+                            .with_label(self.span(token.range.clone()), "block opened here")
+                    );
+                    break;
                 }
-                Ok(( statements, token_range ))
-            } else {
-                Err((format!("expected the start of a block, found {}", token.to_string()), token.range.clone()))
             }
-        } else {
-            Err((format!("expected the start of a block, found nothing"), self.statements[self.statements.len()-1].range.clone()))
-        }
-    }
-
-    fn arglist(&mut self) -> Result<(Vec<Token>, Range<usize>), (String, Range<usize>)> {
-        if let Some(token) = self.tokens.get(self.index) {
-            if let TokenValue::StartArgList = token.value {
-                let mut args: Vec<Token> = Vec::new();
-                loop {
-                    self.index += 1;
-                    if let Some(token) = self.tokens.get(self.index) {
-                        match &token.value {
-                            TokenValue::Number(_) | TokenValue::String(_) | TokenValue::Bool(_) => args.push(token.clone()),
-                            TokenValue::Identifier(_identifier) => {
-                                args.push(token.clone())
-                            },
-                            _ => return Err((format!("found {}, expected Number, String, Bool, or type identifier", token.to_string()), token.range.clone()))
-                        }
+        }
+        self.finish_node();
+        Ok(())
+    }
 
-                        self.index += 1;
-                        if let Some(token) = self.tokens.get(self.index) {
-                            match token.value {
-                                TokenValue::ArgListDeliminator => continue,
-                                TokenValue::EndArgList => break,
-                                _ => return Err((format!("found '{}', expected ','", token.to_string()), token.range.clone()))
-                            }
-                        } else {
-                            return Err((format!("expected ',', found nothing"), token.range.clone()));
-                        }
-                    } else {
-                        return Err((format!("expected Number, String, Bool, or type identifier, found nothing"), token.range.clone()));
-                    }
+    fn arglist(&mut self, kind: ArgListKind) -> Result<(), Diagnostic> {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected start of argument list, found nothing", self.span(self.end_of_input())))
+        };
+        if !matches!(token.value, TokenValue::StartArgList) {
+            return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected start of argument list, found {}", token.to_string()), self.span(token.range)));
+        }
+
+        // Which of these are allowed depends on the kind of argument list, that is checked once the argument is known
+        let expected = "a number, string, translatable string, bool, &reference, binding, object or type";
+        self.start_node(SyntaxKind::ArgList);
+        self.bump();
+        loop {
+            let argument = match self.peek() {
+                Some(argument) if argument.is_literal() || matches!(argument.value, TokenValue::Identifier(_)) => argument,
+                Some(argument) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("found '{}', expected {}", argument.to_string(), expected), self.span(argument.range))),
+                None => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected {}, found nothing", expected), self.span(token.range)))
+            };
+
+            if let (TokenValue::Identifier(_), Some(Token { value: TokenValue::Colon, .. })) = (&argument.value, self.peek_ahead(1)) {
+                if kind == ArgListKind::Plain {
+                    return Err(Diagnostic::error(Code::UnexpectedToken, "named arguments are only allowed in setters, signal handlers, templates and element definitions", self.span(argument.range)));
                 }
-                self.index += 1;
-                Ok(( args, token.range.clone() ))
+                self.start_node(SyntaxKind::NamedArgument);
+                self.bump();
+                self.bump();
+                match self.peek() {
+                    Some(value) if value.is_literal() => self.bump(),
+                    Some(Token { value: TokenValue::Identifier(TokenIdentifierType::Generic(_)), .. }) if kind == ArgListKind::Template => self.bump(),
+                    Some(Token { value: TokenValue::Identifier(TokenIdentifierType::Type(_)), .. }) if kind == ArgListKind::Element => self.bump(),
+                    Some(Token { value: TokenValue::Identifier(TokenIdentifierType::Generic(_)), .. }) if kind == ArgListKind::Element => {
+                        self.start_node(SyntaxKind::ObjectType);
+                        self.bump();
+                        self.arglist(ArgListKind::Plain)?;
+                        self.finish_node();
+                    },
+                    Some(value) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected a value after ':', found {}", value.to_string()), self.span(value.range))),
+                    None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected a value after ':', found nothing", self.span(argument.range)))
+                }
+                self.finish_node();
+            } else if let (ArgListKind::Setter, TokenValue::Identifier(TokenIdentifierType::Generic(_)), Some(Token { value: TokenValue::StartArgList | TokenValue::StartBlock, .. })) = (kind, &argument.value, self.peek_ahead(1)) {
+                self.start_node(SyntaxKind::Object);
+                self.object(argument.clone())?;
+                self.finish_node();
+            } else if let (TokenValue::Identifier(_), Some(Token { value: TokenValue::StartArgList, .. })) = (&argument.value, self.peek_ahead(1)) {
+                if kind != ArgListKind::Plain && kind != ArgListKind::Element {
+                    return Err(Diagnostic::error(Code::UnexpectedToken, "types are only allowed in property definitions", self.span(argument.range)));
+                }
+                self.start_node(SyntaxKind::ObjectType);
+                self.bump();
+                self.arglist(ArgListKind::Plain)?;
+                self.finish_node();
+            } else if let (TokenValue::Identifier(TokenIdentifierType::Generic(keyword)), Some(Token { value: TokenValue::Identifier(_), .. })) = (&argument.value, self.peek_ahead(1)) {
+                if keyword != "bind" {
+                    return Err(Diagnostic::error(Code::UnexpectedToken, format!("found '{}', expected ','", keyword), self.span(argument.range)));
+                }
+                if kind != ArgListKind::Setter {
+                    return Err(Diagnostic::error(Code::UnexpectedToken, "bindings are only allowed in property setters", self.span(argument.range)));
+                }
+                self.start_node(SyntaxKind::Binding);
+                self.bump();
+                let source = self.peek().expect("the source was peeked at");
+                self.bump();
+                match self.peek() {
+                    Some(Token { value: TokenValue::Setter(_), .. }) => self.bump(),
+                    Some(next) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected '.property' after the binding source, found {}", next.to_string()), self.span(next.range))),
+                    None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected '.property' after the binding source, found nothing", self.span(source.range)))
+                }
+                self.finish_node();
             } else {
-                Err((format!("expected start of argument list, found {}", token.to_string()), token.range.clone()))
+                self.bump();
+            }
+
+            match self.peek() {
+                Some(Token { value: TokenValue::ArgListDeliminator, .. }) => self.bump(),
+                Some(Token { value: TokenValue::EndArgList, .. }) => {
+                    self.bump();
+                    break;
+                },
+                Some(next) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("found '{}', expected ','", next.to_string()), self.span(next.range))),
+                None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected ',', found nothing", self.span(argument.range)))
+            }
+        }
+        self.finish_node();
+        Ok(())
+    }
+
+    // The cursor is on the definition token, which might be preceded by doc comments in the same node
+    fn definition(&mut self, token: Token) -> Result<(), Diagnostic> {
+        self.bump();
+        if let TokenValue::Definition(TokenDefinitionType::Object(ref name)) = token.value {
+            if name == "template" {
+                self.arglist(ArgListKind::Template)?;
+                self.block()?;
+                return self.setters();
+            }
+            match self.peek() {
+                Some(Token { value: TokenValue::StartBlock, .. }) => (),
+                Some(inherits @ Token { value: TokenValue::Inherits, .. }) => {
+                    self.start_node(SyntaxKind::Inherits);
+                    self.bump();
+                    match self.peek() {
+                        Some(Token { value: TokenValue::StartArgList, .. }) => self.arglist(ArgListKind::Plain)?,
+                        Some(Token { value: TokenValue::Identifier(TokenIdentifierType::Generic(_)), .. }) => self.bump(),
+                        Some(parent) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected an argument list or definition, found {}", parent.to_string()), self.span(parent.range))),
+                        None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected an argument list or definition, found nothing", self.span(inherits.range)))
+                    }
+                    self.finish_node();
+                },
+                Some(next) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected a '->' or '{{', found '{}'", next.to_string()), self.span(next.range))),
+                None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected block or inherit statement, found nothing", self.span(token.range)))
             }
+            self.block()
+        } else if let TokenValue::Definition(TokenDefinitionType::Element) = token.value {
+            self.arglist(ArgListKind::Element)
         } else {
-            Err((format!("expected start of argument list, found nothing"), self.statements[self.statements.len()-1].range.clone()))
-        }
-    }
-
-    fn definition(&mut self, definition_type: TokenDefinitionType, range: Range<usize>) -> Result<Statement, (String, Range<usize>)> {
-        self.index += 1;
-        if let TokenDefinitionType::Object(name) = definition_type {
-            if let Some(token) = self.tokens.get(self.index) {
-                let mut inherits: Vec<String> = Vec::new();
-                match &token.value {
-                    TokenValue::StartBlock => (),
-                    TokenValue::Inherits => {
-                        self.index += 1;
-                        if let Some(token) = self.tokens.get(self.index) {
-                            match &token.value {
-                                TokenValue::StartArgList => {
-                                    match self.arglist() {
-                                        Ok(arglist) => {
-                                            for token in arglist.0 {
-                                                if let TokenValue::Identifier(TokenIdentifierType::Generic(parent)) = &token.value {
-                                                    inherits.push(parent.clone());
-                                                } else {
-                                                    return Err((String::from("argument list of parents must only contain definitions"), arglist.1.clone()));
-                                                }
-                                            }
-                                        },
-                                        Err(err) => {
-                                            return Err(err);
-                                        }
-                                    }
-                                },
-                                TokenValue::Identifier(TokenIdentifierType::Generic(parent)) => {
-                                    inherits.push(parent.clone());
-                                    self.index += 1;
-                                },
-                                _ => return Err((format!("expected an argument list or definition, found {}", token.to_string()), token.range.clone()))
-                            }
-                        } else {
-                            return Err((format!("expected an argument list or definition, found nothing"), token.range.clone()));
-                        }
-                    },
-                    _ => return Err((format!("expected a '->' or '{{', found '{}'", token.to_string()), token.range.clone()))
+            self.arglist(ArgListKind::Plain)
+        }
+    }
+
+    fn directive(&mut self, token: Token) -> Result<(), Diagnostic> {
+        let directive = match &token.value {
+            TokenValue::Directive(directive_type) => directive_type.directive(),
+            _ => unreachable!("directive() is only called on directive tokens")
+        };
+        self.bump();
+        match &directive.arguments {
+            TokenDirectiveArguments::None => (),
+            TokenDirectiveArguments::One(expected_type) => {
+                match self.peek() {
+                    Some(argument) if argument.is_literal() => self.bump(),
+                    Some(argument) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected {}, found {}", expected_type.to_string(), argument.to_string()), self.span(argument.range))),
+                    None => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected {}, found nothing", expected_type.to_string()), self.span(token.range)))
                 }
-                match self.block() {
-                    Ok(block) => {
-                        let definition_type = {
-                            if block.0.iter().all(|x| matches!(&x.value, StatementValue::Property(_))) {
-                                DefinitionType::Raw
-                            } else if block.0.iter().all(|x| matches!(&x.value, StatementValue::Object(_))) {
-                                if name == "root" {
-                                    let path = Path::new(&self.filename);
-                                    DefinitionType::Root(path.file_stem().expect("invalid file path").to_str().expect("failed to unwrap file path string").to_string())
-                                } else {
-                                    DefinitionType::Collective
-                                }
-                            } else {
-                                return Err((String::from("a definition can only have all property definitions or all objects"), block.1));
-                            }
-                        };
-
-                        let definition = Definition {
-                            name: name.to_string(),
-                            children: block.0,
-                            definition_type,
-                            inherits
-                        };
-
-                        Ok(Statement {
-                            value: StatementValue::Definition(definition),
-                            range: range.clone()
-                        })
-                    },
-                    Err(err) => return Err(err)
+            },
+            TokenDirectiveArguments::Many(_) => {
+                if self.peek().is_none() {
+                    return Err(Diagnostic::error(Code::UnexpectedToken, "expected start of argument list, found nothing", self.span(token.range)));
                 }
-            } else {
-                Err((format!("expected block or inherit statement, found nothing"), range))
+                self.arglist(ArgListKind::Plain)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The `start:` or `internal vbox:` in front of an object, returns the token the object itself starts with
+    fn child_type(&mut self, token: Token) -> Result<Token, Diagnostic> {
+        let length = match (&token.value, self.peek_ahead(1), self.peek_ahead(2)) {
+            (TokenValue::Identifier(TokenIdentifierType::Generic(keyword)), Some(Token { value: TokenValue::Identifier(_), .. }), Some(Token { value: TokenValue::Colon, .. })) if keyword == "internal" => 3,
+            (_, Some(Token { value: TokenValue::Colon, .. }), _) => 2,
+            _ => return Ok(token)
+        };
+
+        self.start_node(SyntaxKind::ChildType);
+        for _ in 0..length {
+            self.bump();
+        }
+        self.finish_node();
+        match self.peek() {
+            Some(next @ Token { value: TokenValue::Identifier(_), .. }) => Ok(next),
+            Some(next) => Err(Diagnostic::error(Code::UnexpectedToken, format!("expected an object after the child type, found {}", next.to_string()), self.span(next.range))),
+            None => Err(Diagnostic::error(Code::UnexpectedToken, "expected an object after the child type, found nothing", self.span(token.range)))
+        }
+    }
+
+    fn object(&mut self, token: Token) -> Result<(), Diagnostic> {
+        if let TokenValue::Identifier(TokenIdentifierType::Type(_)) = token.value {
+            return Err(Diagnostic::error(Code::UnexpectedToken, "expected generic identifier, found type identifier", self.span(token.range)));
+        }
+        self.bump();
+
+        match self.peek() {
+            Some(Token { value: TokenValue::StartArgList, .. }) => {
+                self.arglist(ArgListKind::Plain)?;
+                if let Some(Token { value: TokenValue::StartBlock, .. }) = self.peek() {
+                    self.block()?;
+                }
+            },
+            Some(Token { value: TokenValue::StartBlock, .. }) => self.block()?,
+            Some(next) => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected the start of an argument list or block, found '{}'", next.to_string()), self.span(next.range))),
+            None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected argument list or block, found nothing", self.span(token.range)))
+        }
+        self.setters()
+    }
+
+    // The setters after an object or template
+    fn setters(&mut self) -> Result<(), Diagnostic> {
+        while let Some(next) = self.peek() {
+            match next.value {
+                TokenValue::Identifier(_) | TokenValue::EndBlock | TokenValue::Definition(_) | TokenValue::Directive(_) | TokenValue::DocComment(_) => break,
+                // The end of an object given to a setter
+                TokenValue::ArgListDeliminator | TokenValue::EndArgList => break,
+                TokenValue::Setter(ref name) => {
+                    self.start_node(SyntaxKind::Setter);
+                    self.bump();
+                    let prefixed = (name == "layout" || name == "accessible") && matches!(self.peek(), Some(Token { value: TokenValue::Setter(_), .. }));
+                    if prefixed {
+                        self.bump();
+                    }
+                    self.arglist(match name.as_str() {
+                        _ if prefixed => ArgListKind::Plain,
+                        "on" => ArgListKind::Signal,
+                        "class" => ArgListKind::Plain,
+                        _ => ArgListKind::Setter
+                    })?;
+                    self.finish_node();
+                },
+                _ => return Err(Diagnostic::error(Code::UnexpectedToken, format!("expected setter, found {}", next.to_string()), self.span(next.range)))
             }
+        }
+        Ok(())
+    }
+
+    // Doc comments are part of the definition they document
+    fn documented(&mut self) -> Result<(), Diagnostic> {
+        let documented = self.tokens[self.index..].iter()
+            .find(|token| !token.is_trivia() && !matches!(token.value, TokenValue::DocComment(_)))
+            .cloned();
+
+        match documented {
+            Some(token @ Token { value: TokenValue::Definition(_), .. }) => {
+                self.start_node(Parser::definition_kind(&token));
+                while let Some(Token { value: TokenValue::DocComment(_), .. }) = self.peek() {
+                    self.bump();
+                }
+                self.definition(token)?;
+                self.finish_node();
+            },
+            // Doc comments that document nothing are kept like regular comments, they only get a warning
+            _ => {
+                let start = self.peek().expect("documented is only called on a doc comment").range.start;
+                let mut end = start;
+                while let Some(Token { value: TokenValue::DocComment(_), range }) = self.peek() {
+                    end = range.end;
+                    self.bump();
+                }
+                self.warnings.push(
+                    Diagnostic::warning(Code::DanglingDocComment, "doc comments can only document definitions and property definitions", self.span(start..end))
+                        .with_help("use '//' for a regular comment")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn definition_kind(token: &Token) -> SyntaxKind {
+        if let TokenValue::Definition(TokenDefinitionType::Object(_)) = token.value {
+            SyntaxKind::Definition
+        } else {
+            SyntaxKind::PropertyDefinition
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), Diagnostic> {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err(Diagnostic::error(Code::UnexpectedToken, "expected definition, directive, or identifier, found nothing", self.span(self.end_of_input())))
+        };
+        let kind = match &token.value {
+            TokenValue::Definition(_) => Parser::definition_kind(&token),
+            TokenValue::Directive(_) => SyntaxKind::Directive,
+            TokenValue::Identifier(_) => SyntaxKind::Object,
+            TokenValue::DocComment(_) => return self.documented(),
+            _ => return Err(Diagnostic::error(Code::UnexpectedToken, format!("unexpected {}", token.to_string()), self.span(token.range)))
+        };
+
+        self.start_node(kind);
+        match kind {
+            SyntaxKind::Directive => self.directive(token)?,
+            SyntaxKind::Object => {
+                let token = self.child_type(token)?;
+                self.object(token)?
+            },
+            _ => self.definition(token)?
+        }
+        self.finish_node();
+        Ok(())
+    }
+
+    // Lowering Functions
+
+    fn doc(node: &SyntaxNode) -> Option<String> {
+        let doc: Vec<&str> = node.tokens()
+            .filter_map(|token| match &token.value {
+                TokenValue::DocComment(line) => Some(line.as_str()),
+                _ => None
+            })
+            .collect();
+        if doc.is_empty() {
+            None
         } else {
-            match self.arglist() {
-                Ok(arglist) => {
-                    if arglist.0.len() != 2 {
-                        return Err((format!("expected only 2 arguments, found {} args", arglist.0.len()), arglist.1));
+            Some(doc.join("\n"))
+        }
+    }
+
+    // The first token that isn't a doc comment, which is what the statement is named after
+    fn head(node: &SyntaxNode) -> Token {
+        node.tokens()
+            .find(|token| !matches!(token.value, TokenValue::DocComment(_)))
+            .cloned()
+            .expect("parsed statements start with a token")
+    }
+
+    // The span of a statement from its head to its end, leaving out the doc comments in front of it
+    fn statement_span(&self, node: &SyntaxNode, head: &Token) -> Span {
+        let end = node.range().map_or(head.range.end, |range| range.end);
+        self.span(head.range.start..end)
+    }
+
+    // A block that was never closed gets an empty closing brace at its end
+    fn braces(&self, block: &SyntaxNode) -> Braces {
+        let open = block.tokens().next().expect("parsed blocks start with '{'").range.clone();
+        let close = match block.tokens().last() {
+            Some(token @ Token { value: TokenValue::EndBlock, .. }) => token.range.clone(),
+            _ => {
+                let end = block.range().map_or(open.end, |range| range.end);
+                end..end
+            }
+        };
+        Braces {
+            open: self.span(open),
+            close: self.span(close)
+        }
+    }
+
+    // The arguments of an argument list, without the punctuation
+    fn arguments(node: &SyntaxNode) -> Vec<Token> {
+        node.tokens()
+            .filter(|token| !matches!(token.value, TokenValue::StartArgList | TokenValue::ArgListDeliminator | TokenValue::EndArgList))
+            .cloned()
+            .collect()
+    }
+
+    // The literals of an argument list. Anything else is an error, except for invalid literals which have already been
+    // reported, so they are left out. Bindings and objects are only taken as the single value of a setter, which is
+    // handled before this.
+    fn literals(&mut self, node: &SyntaxNode) -> Vec<Literal> {
+        let expected = "a string, translatable string, number, bool or &reference";
+        let mut literals = Vec::new();
+        for token in Parser::arguments(node) {
+            if let Some(value) = Value::from(&token) {
+                literals.push(Literal { value, span: self.span(token.range) });
+            } else if !matches!(token.value, TokenValue::Error) {
+                self.errors.push(Diagnostic::error(Code::InvalidValue, format!("expected {}, found {}", expected, token.to_string()), self.span(token.range)));
+            }
+        }
+        for child in node.nodes().filter(|child| child.kind != SyntaxKind::NamedArgument) {
+            let found = match child.kind {
+                SyntaxKind::Binding => "a binding",
+                SyntaxKind::Object => "an object",
+                _ => "a type"
+            };
+            if let Some(range) = child.range() {
+                let diagnostic = Diagnostic::error(Code::InvalidValue, format!("expected {}, found {}", expected, found), self.span(range));
+                match child.kind {
+                    SyntaxKind::Binding | SyntaxKind::Object => self.errors.push(diagnostic.with_help(format!("{} can only be given to a setter on its own", found))),
+                    _ => self.errors.push(diagnostic)
+                }
+            }
+        }
+        literals
+    }
+
+    fn lower_block(&mut self, node: &SyntaxNode) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        for child in node.nodes().filter(|child| child.kind != SyntaxKind::Error) {
+            match self.lower_statement(child) {
+                Ok(statement) => {
+                    match &statement.value {
+                        StatementValue::Property(_) | StatementValue::SignalDefinition(_) | StatementValue::ChildTypeDefinition(_) | StatementValue::RoleDefinition(_) | StatementValue::ElementDefinition(_) | StatementValue::Object(_) | StatementValue::Menu(_) => statements.push(statement),
+                        _ => self.errors.push(Diagnostic::error(Code::MisplacedStatement, format!("found {} inside block. Only properties and objects are allowed here.", statement.to_string()), statement.span)),
                     }
-                    
-                    let name = &arglist.0[0];
-                    if let TokenValue::String(name) = &name.value {
-                        let internal_type = &arglist.0[1];
-                        if let TokenValue::Identifier(TokenIdentifierType::Type(internal_type)) = &internal_type.value {
-                            let property = Property {
-                                name: name.clone(),
-                                internal_type: internal_type.clone(),
-                                definition_type: definition_type.clone()
-                            };
-                            Ok(Statement {
-                                value: StatementValue::Property(property),
-                                range: range.clone()
-                            })
+                },
+                Err(err) => self.errors.push(err)
+            }
+        }
+        statements
+    }
+
+    fn lower_definition(&mut self, node: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        let token = Parser::head(node);
+        let name = match &token.value {
+            TokenValue::Definition(TokenDefinitionType::Object(name)) => name.clone(),
+            _ => unreachable!("definition nodes start with an object definition")
+        };
+        if name == "template" {
+            return self.lower_template(node, &token);
+        }
+
+        let mut inherits: Vec<(String, Span)> = Vec::new();
+        if let Some(inherits_node) = node.node(SyntaxKind::Inherits) {
+            match inherits_node.node(SyntaxKind::ArgList) {
+                Some(arglist) => {
+                    for token in Parser::arguments(arglist) {
+                        if let TokenValue::Identifier(TokenIdentifierType::Generic(parent)) = &token.value {
+                            inherits.push((parent.clone(), self.span(token.range.clone())));
                         } else {
-                            return Err((format!("expected type identifier, found {}", internal_type.to_string()), internal_type.range.clone()));
+                            return Err(Diagnostic::error(Code::UnexpectedToken, "argument list of parents must only contain definitions", self.span(token.range)));
                         }
-                    } else {
-                        return Err((format!("expected String, found {}", name.to_string()), name.range.clone()));
                     }
                 },
-                Err(err) => return Err(err)
+                None => {
+                    for token in inherits_node.tokens() {
+                        if let TokenValue::Identifier(TokenIdentifierType::Generic(parent)) = &token.value {
+                            inherits.push((parent.clone(), self.span(token.range.clone())));
+                        }
+                    }
+                }
             }
         }
+
+        let block = node.node(SyntaxKind::Block).expect("parsed definitions have a block");
+        let braces = self.braces(block);
+        let children = self.lower_block(block);
+        let definition_type = {
+            if children.iter().all(|x| matches!(&x.value, StatementValue::Property(_) | StatementValue::SignalDefinition(_) | StatementValue::ChildTypeDefinition(_) | StatementValue::RoleDefinition(_) | StatementValue::ElementDefinition(_))) {
+                DefinitionType::Raw
+            } else if children.iter().all(|x| matches!(&x.value, StatementValue::Object(_) | StatementValue::Menu(_))) {
+                if name == "root" {
+                    let path = Path::new(&self.filename);
+                    DefinitionType::Root(path.file_stem().expect("invalid file path").to_str().expect("failed to unwrap file path string").to_string())
+                } else {
+                    DefinitionType::Collective
+                }
+            } else {
+                return Err(Diagnostic::error(Code::MixedDefinition, "a definition can only have all property definitions or all objects", braces.open.to(&braces.close)));
+            }
+        };
+
+        let definition = Definition {
+            name,
+            name_span: self.span(token.range.clone()),
+            children,
+            inherits,
+            block: braces,
+            definition_type
+        };
+
+        Ok(Statement {
+            value: StatementValue::Definition(definition),
+            span: self.statement_span(node, &token),
+            doc: Parser::doc(node)
+        })
     }
 
-    fn directive(&mut self, directive_type: TokenDirectiveType, range: Range<usize>) -> Result<Statement, (String, Range<usize>)> {
-        self.index += 1;
-        if let Some(token) = self.tokens.get(self.index) {
-            if let TokenValue::String(arg) = &token.value {
-                self.index += 1;
-                let value = match directive_type {
-                    TokenDirectiveType::Header => {
-                        StatementValue::Header(arg.clone())
-                    },
-                    TokenDirectiveType::Include => {
-                        StatementValue::Include(arg.clone())
+    fn lower_template(&mut self, node: &SyntaxNode, token: &Token) -> Result<Statement, Diagnostic> {
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed templates have an argument list");
+        let arglist_span = self.span(arglist.range().unwrap_or(token.range.clone()));
+        let (class, class_span) = match Parser::arguments(arglist).as_slice() {
+            [Token { value: TokenValue::String(class), range }] => (class.clone(), self.span(range.clone())),
+            _ => return Err(
+                Diagnostic::error(Code::InvalidTemplate, "expected the name of the template's class", arglist_span)
+                    .with_help("templates are written like @template(\"MyWindow\", parent: GtkApplicationWindow)")
+            )
+        };
+
+        let mut parent = None;
+        for named in arglist.nodes().filter(|child| child.kind == SyntaxKind::NamedArgument) {
+            let tokens: Vec<&Token> = named.tokens().collect();
+            let (name, value) = (tokens[0], tokens[2]);
+            let option = match &name.value {
+                TokenValue::Identifier(TokenIdentifierType::Generic(option)) => option.as_str(),
+                TokenValue::Identifier(TokenIdentifierType::Type(option)) => option.to_string(),
+                _ => unreachable!("named arguments start with an identifier")
+            };
+            match (option, &value.value) {
+                ("parent", TokenValue::Identifier(TokenIdentifierType::Generic(parent_name))) => {
+                    if parent.replace((parent_name.clone(), self.span(value.range.clone()))).is_some() {
+                        return Err(Diagnostic::error(Code::InvalidTemplate, "'parent' is given more than once", self.span(name.range.clone())));
                     }
+                },
+                ("parent", _) => return Err(Diagnostic::error(Code::InvalidTemplate, format!("'parent' expects a definition, found {}", value.to_string()), self.span(value.range.clone()))),
+                _ => return Err(Diagnostic::error(Code::InvalidTemplate, format!("unknown option '{}', expected 'parent'", option), self.span(name.range.clone())))
+            }
+        }
+        let (parent, parent_span) = match parent {
+            Some(parent) => parent,
+            None => return Err(
+                Diagnostic::error(Code::InvalidTemplate, format!("the template '{}' has no parent", class), arglist_span)
+                    .with_help(format!("add the class it derives from, like @template(\"{}\", parent: GtkWidget)", class))
+            )
+        };
+
+        let block = node.node(SyntaxKind::Block).expect("parsed templates have a block");
+        let braces = self.braces(block);
+        let children = self.lower_block(block);
+        if let Some(child) = children.iter().find(|child| !matches!(child.value, StatementValue::Object(_))) {
+            return Err(Diagnostic::error(Code::InvalidTemplate, format!("found {} inside template, only objects are allowed here", child.to_string()), child.span.clone()));
+        }
+
+        let mut object = Object {
+            name: parent.clone(),
+            name_span: parent_span,
+            children,
+            block: Some(braces.clone()),
+            arguments: Vec::new(),
+            arguments_span: None,
+            setters: Vec::new(),
+            signals: Vec::new(),
+            child_type: None,
+            layout: Vec::new(),
+            accessible: Vec::new(),
+            classes: Vec::new()
+        };
+        self.lower_setters(node, &mut object);
+
+        let definition = Definition {
+            name: String::from("template"),
+            name_span: self.span(token.range.clone()),
+            children: Vec::new(),
+            inherits: Vec::new(),
+            block: braces,
+            definition_type: DefinitionType::Template(Box::new(Template { class, class_span, parent, object }))
+        };
+
+        Ok(Statement {
+            value: StatementValue::Definition(definition),
+            span: self.statement_span(node, token),
+            doc: Parser::doc(node)
+        })
+    }
+
+    fn lower_property(&mut self, node: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        let token = Parser::head(node);
+        let definition_type = match &token.value {
+            TokenValue::Definition(definition_type) => definition_type.clone(),
+            _ => unreachable!("property definition nodes start with a definition")
+        };
+
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed property definitions have an argument list");
+        let arglist_range = arglist.range().unwrap_or(token.range.clone());
+        let arguments = Parser::arguments(arglist);
+        let object_type = arglist.node(SyntaxKind::ObjectType);
+        if let TokenDefinitionType::Signal | TokenDefinitionType::ChildType | TokenDefinitionType::AccessibleRole = definition_type {
+            return self.lower_name_definition(node, &token, &definition_type, &arguments, arglist_range);
+        }
+        if let TokenDefinitionType::Element = definition_type {
+            return self.lower_element_definition(node, &token, arglist);
+        }
+        let count = arguments.len() + arglist.nodes().count();
+        if count != 2 {
+            return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected only 2 arguments, found {} args", count), self.span(arglist_range)));
+        }
+
+        let name = &arguments[0];
+        if let TokenValue::String(name_value) = &name.value {
+            let internal_type = match object_type {
+                Some(object_type) => Some((self.lower_object_type(object_type)?, object_type.range().expect("parsed types have tokens"))),
+                None => match &arguments[1].value {
+                    TokenValue::Identifier(TokenIdentifierType::Type(internal_type_value)) => Some((internal_type_value.clone(), arguments[1].range.clone())),
+                    _ => None
+                }
+            };
+            if let Some((internal_type_value, type_range)) = internal_type {
+                // Relations point at other objects, GtkBuilder reads their value as an id
+                if matches!(definition_type, TokenDefinitionType::AccessibleRelation) && !matches!(internal_type_value, TokenTypeIdentifierType::Object(_)) {
+                    return Err(
+                        Diagnostic::error(Code::InvalidPropertyDefinition, format!("accessible relations must have an Object type, found {}", internal_type_value.to_string()), self.span(type_range))
+                            .with_help("relations are declared like @AccessibleRelation(\"labelled-by\", Object(\"GtkWidget\"))")
+                    );
+                }
+                let property = Property {
+                    name: name_value.clone(),
+                    internal_type: internal_type_value,
+                    definition_type,
+                    name_span: self.span(name.range.clone()),
+                    type_span: self.span(type_range)
                 };
                 Ok(Statement {
-                    value,
-                    range: range.clone()
+                    value: StatementValue::Property(property),
+                    span: self.statement_span(node, &token),
+                    doc: Parser::doc(node)
                 })
             } else {
-                Err((format!("expected string, found {}", token.to_string()), token.range.clone()))
+                Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected type identifier, found {}", arguments[1].to_string()), self.span(arguments[1].range.clone())))
             }
         } else {
-            Err((format!("expected string, found nothing"), range))
+            Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected String, found {}", name.to_string()), self.span(name.range.clone())))
         }
     }
 
-    fn object(&mut self, identifier_type: TokenIdentifierType, range: Range<usize>) -> Result<Statement, (String, Range<usize>)> {
-        if let TokenIdentifierType::Generic(name) = identifier_type {
-            self.index += 1;
-            if let Some(token) = self.tokens.get(self.index) {
-                let mut arguments = Vec::new();
-                let mut children = Vec::new();
-                let mut setters = Vec::new();
-                let token_range = token.range.clone(); // TODO there might be a better way of handling this
-                
-                match token.value {
-                    TokenValue::StartArgList => {
-                        match self.arglist() {
-                            Ok(args) => {
-                                arguments = args.0;
-                                if let Some(token) = self.tokens.get(self.index) {
-                                    if let TokenValue::StartBlock = token.value {
-                                        match self.block() {
-                                            Ok(c) => children = c.0,
-                                            Err(e) => return Err(e)
-                                        }
-                                    }
-                                } else {
-                                    return Err((format!("expected block, found nothing"), token_range))
-                                }
-                            },
-                            Err(err) => return Err(err)
-                        }
-                    },
-                    TokenValue::StartBlock => {
-                        match self.block() {
-                            Ok(c) => children = c.0,
-                            Err(e) => return Err(e)
-                        }
-                    },
-                    _ => return Err((format!("expected the start of an argument list or block, found '{}'", token.to_string()), token.range.clone()))
+    // @Element("item", "items", String, id: Optional(String))
+    fn lower_element_definition(&mut self, node: &SyntaxNode, token: &Token, arglist: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        let arglist_range = arglist.range().unwrap_or(token.range.clone());
+        let arguments = Parser::arguments(arglist);
+        let (name, name_range, container) = match arguments.as_slice() {
+            [Token { value: TokenValue::String(name), range }, Token { value: TokenValue::String(container), .. }, ..] => (name.clone(), range.clone(), container.clone()),
+            _ => return Err(
+                Diagnostic::error(Code::InvalidPropertyDefinition, "expected the name of the element and of the element holding it", self.span(arglist_range))
+                    .with_help("elements are declared like @Element(\"item\", \"items\", String)")
+            )
+        };
+        let text = match &arguments[2..] {
+            [] => None,
+            [Token { value: TokenValue::Identifier(TokenIdentifierType::Type(text)), .. }] => Some(text.clone()),
+            [argument, ..] => return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected the type of the element's text, found {}", argument.to_string()), self.span(argument.range.clone())))
+        };
+        if let Some(object_type) = arglist.node(SyntaxKind::ObjectType) {
+            return Err(Diagnostic::error(Code::InvalidPropertyDefinition, "the text of an element can't be an object", self.span(object_type.range().expect("parsed types have tokens"))));
+        }
+
+        let mut attributes: Vec<ElementAttribute> = Vec::new();
+        for named in arglist.nodes().filter(|child| child.kind == SyntaxKind::NamedArgument) {
+            let tokens: Vec<&Token> = named.tokens().collect();
+            let attribute = match &tokens[0].value {
+                TokenValue::Identifier(TokenIdentifierType::Generic(attribute)) => attribute.as_str(),
+                TokenValue::Identifier(TokenIdentifierType::Type(attribute)) => attribute.to_string(),
+                _ => unreachable!("named arguments start with an identifier")
+            }.to_string();
+            if attributes.iter().any(|declared| declared.name == attribute) {
+                return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("'{}' is declared more than once", attribute), self.span(tokens[0].range.clone())));
+            }
+            let (value_type, optional) = match (tokens.get(2), named.node(SyntaxKind::ObjectType)) {
+                (Some(Token { value: TokenValue::Identifier(TokenIdentifierType::Type(value_type)), .. }), _) => (value_type.clone(), false),
+                (_, Some(object_type)) => self.lower_attribute_type(object_type)?,
+                (Some(value), _) => return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected type identifier, found {}", value.to_string()), self.span(value.range.clone()))),
+                (None, None) => unreachable!("named arguments have a value")
+            };
+            attributes.push(ElementAttribute { name: attribute, value_type, optional });
+        }
+
+        Ok(Statement {
+            value: StatementValue::ElementDefinition(ElementDefinition {
+                name,
+                name_span: self.span(name_range),
+                container,
+                text,
+                attributes
+            }),
+            span: self.statement_span(node, token),
+            doc: Parser::doc(node)
+        })
+    }
+
+    // Object("GtkWidget"), Optional(String) or Optional(Object("GtkWidget"))
+    fn lower_attribute_type(&self, node: &SyntaxNode) -> Result<(TokenTypeIdentifierType, bool), Diagnostic> {
+        let token = Parser::head(node);
+        match &token.value {
+            TokenValue::Identifier(TokenIdentifierType::Generic(name)) if name == "Optional" => (),
+            _ => return Ok((self.lower_object_type(node)?, false))
+        }
+
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed types have an argument list");
+        match (Parser::arguments(arglist).as_slice(), arglist.node(SyntaxKind::ObjectType)) {
+            ([Token { value: TokenValue::Identifier(TokenIdentifierType::Type(value_type)), .. }], None) => Ok((value_type.clone(), true)),
+            ([], Some(object_type)) => Ok((self.lower_object_type(object_type)?, true)),
+            _ => Err(
+                Diagnostic::error(Code::InvalidPropertyDefinition, "expected the type of the optional attribute", self.span(arglist.range().unwrap_or(token.range)))
+                    .with_help("optional attributes are declared like Optional(String)")
+            )
+        }
+    }
+
+    // Object("GtkWidget")
+    fn lower_object_type(&self, node: &SyntaxNode) -> Result<TokenTypeIdentifierType, Diagnostic> {
+        let token = Parser::head(node);
+        match &token.value {
+            TokenValue::Identifier(TokenIdentifierType::Generic(name)) if name == "Object" => (),
+            TokenValue::Identifier(TokenIdentifierType::Generic(name)) => return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("unknown type '{}', expected Object", name), self.span(token.range))),
+            _ => return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("found {}, expected Object", token.to_string()), self.span(token.range)))
+        }
+
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed types have an argument list");
+        let arguments = Parser::arguments(arglist);
+        match arguments.as_slice() {
+            [Token { value: TokenValue::String(class), .. }] => Ok(TokenTypeIdentifierType::Object(class.clone())),
+            _ => Err(
+                Diagnostic::error(Code::InvalidPropertyDefinition, "expected the name of a class", self.span(arglist.range().unwrap_or(token.range)))
+                    .with_help("object types are written like Object(\"GtkWidget\")")
+            )
+        }
+    }
+
+    fn lower_name_definition(&mut self, node: &SyntaxNode, token: &Token, definition_type: &TokenDefinitionType, arguments: &[Token], arglist_range: Range<usize>) -> Result<Statement, Diagnostic> {
+        if arguments.len() != 1 {
+            return Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected only 1 argument, found {} args", arguments.len()), self.span(arglist_range)));
+        }
+
+        let name = &arguments[0];
+        if let TokenValue::String(name_value) = &name.value {
+            let definition = NameDefinition {
+                name: name_value.clone(),
+                name_span: self.span(name.range.clone())
+            };
+            Ok(Statement {
+                value: match definition_type {
+                    TokenDefinitionType::ChildType => StatementValue::ChildTypeDefinition(definition),
+                    TokenDefinitionType::AccessibleRole => StatementValue::RoleDefinition(definition),
+                    _ => StatementValue::SignalDefinition(definition)
+                },
+                span: self.statement_span(node, token),
+                doc: Parser::doc(node)
+            })
+        } else {
+            Err(Diagnostic::error(Code::InvalidPropertyDefinition, format!("expected String, found {}", name.to_string()), self.span(name.range.clone())))
+        }
+    }
+
+    fn directive_argument(&self, argument: &Token, expected_type: &TokenTypeIdentifierType) -> Result<Option<Value>, Diagnostic> {
+        match Value::from(argument) {
+            Some(value) if value.is_of_type(expected_type) => Ok(Some(value)),
+            None if matches!(argument.value, TokenValue::Error) => Ok(None),
+            _ => Err(Diagnostic::error(Code::InvalidDirectiveArguments, format!("expected {}, found {}", expected_type.to_string(), argument.to_string()), self.span(argument.range.clone())))
+        }
+    }
+
+    fn lower_directive(&mut self, node: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        let token = Parser::head(node);
+        let directive_type = match &token.value {
+            TokenValue::Directive(directive_type) => directive_type.clone(),
+            _ => unreachable!("directive nodes start with a directive")
+        };
+        let directive = directive_type.directive();
+
+        // Invalid literals have no value, their error has already been reported and the directive is never used
+        let arguments: Vec<Option<Value>> = match &directive.arguments {
+            TokenDirectiveArguments::None => Vec::new(),
+            TokenDirectiveArguments::One(expected_type) => {
+                let argument = node.tokens().nth(1).cloned().expect("parsed directives have their argument");
+                vec![self.directive_argument(&argument, expected_type)?]
+            },
+            TokenDirectiveArguments::Many(expected_types) => {
+                let arglist = node.node(SyntaxKind::ArgList).expect("parsed directives have their argument list");
+                let arguments = Parser::arguments(arglist);
+                if arguments.len() != expected_types.len() {
+                    return Err(Diagnostic::error(Code::InvalidDirectiveArguments, format!("'#{}' expects {} arguments, found {}", directive.name, expected_types.len(), arguments.len()), self.span(arglist.range().unwrap_or(token.range))));
+                }
+                arguments.iter().zip(expected_types.iter())
+                    .map(|(argument, expected_type)| self.directive_argument(argument, expected_type))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        let string = |index: usize| arguments[index].as_ref().map(Value::value_to_string).unwrap_or_default();
+        let value = match directive_type {
+            TokenDirectiveType::Header => StatementValue::Header(string(0)),
+            TokenDirectiveType::Include => StatementValue::Include(string(0)),
+            TokenDirectiveType::Requires => StatementValue::Requires(string(0), string(1))
+        };
+        Ok(Statement {
+            value,
+            span: self.statement_span(node, &token),
+            doc: None
+        })
+    }
+
+    // bind source.property, followed by the binding's flags
+    fn lower_binding(&mut self, arglist: &SyntaxNode, node: &SyntaxNode) -> Option<Binding> {
+        let tokens: Vec<&Token> = node.tokens().collect();
+        let (source, property) = match (&tokens[1].value, &tokens[2].value) {
+            (TokenValue::Identifier(TokenIdentifierType::Generic(source)), TokenValue::Setter(property)) => (source.clone(), property.clone()),
+            (TokenValue::Identifier(_), _) => {
+                self.errors.push(Diagnostic::error(Code::MalformedBinding, "expected the id of an object, found type identifier", self.span(tokens[1].range.clone())));
+                return None;
+            },
+            _ => unreachable!("parsed bindings have a source and a property")
+        };
+        let range = node.range().expect("parsed bindings have tokens");
+
+        let mut flags = Vec::new();
+        for token in Parser::arguments(arglist) {
+            let flag = match &token.value {
+                TokenValue::Identifier(TokenIdentifierType::Generic(flag)) if token.range.start > range.start => flag,
+                TokenValue::Identifier(TokenIdentifierType::Generic(_)) => {
+                    self.errors.push(Diagnostic::error(Code::MalformedBinding, "binding flags come after the binding", self.span(token.range)));
+                    continue;
+                },
+                _ => {
+                    self.errors.push(
+                        Diagnostic::error(Code::MalformedBinding, format!("expected a binding flag, found {}", token.to_string()), self.span(token.range))
+                            .with_help("bindings are written like .visible(bind toggle.active, sync-create)")
+                    );
+                    continue;
                 }
+            };
+            if BINDING_FLAGS.contains(&flag.as_str()) {
+                flags.push(flag.clone());
+            } else {
+                self.errors.push(
+                    Diagnostic::error(Code::MalformedBinding, format!("unknown binding flag '{}'", flag), self.span(token.range))
+                        .with_note(format!("the flags are {}", BINDING_FLAGS.join(", ")))
+                        .with_suggestion(flag, BINDING_FLAGS.iter().copied())
+                );
+            }
+        }
 
-                loop {
-                    if let Some(token) = self.tokens.get(self.index) {
-                        let token_range = token.range.clone();
-                        match &token.value {
-                            TokenValue::Identifier(_) | TokenValue::EndBlock => break,
-                            TokenValue::Setter(name) => {
-                                self.index += 1;
-                                let name = name.clone();
-
-                                match self.arglist() {
-                                    Ok(args) => {
-                                        if args.0.len() != 1 {
-                                            return Err((format!("expected 1 argument, got {}", args.0.len()), args.1));
-                                        }
-
-                                        let value = &args.0[0];
-
-                                        match &value.value {
-                                            TokenValue::Number(_) | TokenValue::String(_) | TokenValue::Bool(_) => {
-                                                setters.push(Setter {
-                                                    name: name,
-                                                    value: value.clone(),
-                                                    range: token_range
-                                                })
-                                            },
-                                            _ => return Err((format!("expected Number, String, or Bool, found {}", value.to_string()), value.range.clone()))
-                                        }
-                                    },
-                                    Err(err) => return Err(err)
-                                }
-                            },
-                            _ => {
-                                return Err((format!("expected setter, found {}", token.to_string()), token_range));
-                            }
-                        }
+        Some(Binding {
+            source,
+            source_span: self.span(tokens[1].range.clone()),
+            property,
+            property_span: self.span((tokens[2].range.start + 1)..tokens[2].range.end),
+            flags,
+            span: self.span(range)
+        })
+    }
+
+    // .class("suggested-action", "pill"), every argument is a class name
+    fn lower_classes(&mut self, node: &SyntaxNode) -> Vec<(String, Span)> {
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed setters have an argument list");
+        let mut classes = Vec::new();
+        for literal in self.literals(arglist) {
+            match literal.value {
+                Value::String(class) => classes.push((class, literal.span)),
+                value => self.errors.push(Diagnostic::error(Code::InvalidValue, format!("expected the name of a style class, found {}", value.to_string()), literal.span))
+            }
+        }
+        classes
+    }
+
+    // .on("signal", "handler", swapped: true, after: false, object: "id")
+    fn lower_signal(&mut self, node: &SyntaxNode, token: &Token) -> Option<Signal> {
+        let arglist = node.node(SyntaxKind::ArgList).expect("parsed setters have an argument list");
+        let arguments = Parser::arguments(arglist);
+        if arguments.len() != 2 {
+            self.errors.push(
+                Diagnostic::error(Code::InvalidSignal, format!("expected the name of a signal and a handler, found {} positional args", arguments.len()), self.span(arglist.range().unwrap_or(token.range.clone())))
+                    .with_help("signal handlers are written like .on(\"clicked\", \"on_clicked\")")
+            );
+            return None;
+        }
+
+        let mut names = Vec::new();
+        for argument in &arguments {
+            match &argument.value {
+                TokenValue::String(name) => names.push(name.clone()),
+                TokenValue::Error => return None,
+                _ => {
+                    self.errors.push(Diagnostic::error(Code::InvalidSignal, format!("expected String, found {}", argument.to_string()), self.span(argument.range.clone())));
+                    return None;
+                }
+            }
+        }
+
+        let mut signal = Signal {
+            handler: names.pop().expect("there are two names"),
+            name: names.pop().expect("there are two names"),
+            name_span: self.span(arguments[0].range.clone()),
+            swapped: None,
+            after: None,
+            object: None,
+            span: self.statement_span(node, token)
+        };
+
+        for named in arglist.nodes().filter(|child| child.kind == SyntaxKind::NamedArgument) {
+            let tokens: Vec<&Token> = named.tokens().collect();
+            let (name, value) = (tokens[0], tokens[2]);
+            let option = match &name.value {
+                TokenValue::Identifier(TokenIdentifierType::Generic(option)) => option.as_str(),
+                TokenValue::Identifier(TokenIdentifierType::Type(option)) => option.to_string(),
+                _ => unreachable!("named arguments start with an identifier")
+            };
+
+            let given_twice = match (option, &value.value) {
+                (_, TokenValue::Error) => continue,
+                ("swapped", TokenValue::Bool(swapped)) => signal.swapped.replace(*swapped).is_some(),
+                ("after", TokenValue::Bool(after)) => signal.after.replace(*after).is_some(),
+                ("object", TokenValue::String(object)) => signal.object.replace((object.clone(), self.span(value.range.clone()))).is_some(),
+                ("swapped" | "after" | "object", _) => {
+                    let expected = if option == "object" { "String" } else { "Bool" };
+                    self.errors.push(Diagnostic::error(Code::InvalidSignal, format!("'{}' expects {}, found {}", option, expected, value.to_string()), self.span(value.range.clone())));
+                    continue;
+                },
+                _ => {
+                    self.errors.push(Diagnostic::error(Code::InvalidSignal, format!("unknown option '{}', expected 'swapped', 'after' or 'object'", option), self.span(name.range.clone())));
+                    continue;
+                }
+            };
+            if given_twice {
+                self.errors.push(Diagnostic::error(Code::InvalidSignal, format!("'{}' is given more than once", option), self.span(name.range.clone())));
+            }
+        }
+
+        Some(signal)
+    }
+
+    fn lower_object(&mut self, node: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        let token = Parser::head(node);
+        let name = match &token.value {
+            TokenValue::Identifier(TokenIdentifierType::Generic(name)) => name.clone(),
+            _ => unreachable!("object nodes start with a generic identifier")
+        };
+
+        let arglist = node.node(SyntaxKind::ArgList);
+        let arguments = match arglist {
+            Some(arglist) => self.literals(arglist),
+            None => Vec::new()
+        };
+        let block = node.node(SyntaxKind::Block);
+        let children = match block {
+            Some(block) => self.lower_block(block),
+            None => Vec::new()
+        };
+
+        let child_type = node.node(SyntaxKind::ChildType).map(|child_type| {
+            let tokens: Vec<&Token> = child_type.tokens().collect();
+            let internal = tokens.len() == 3;
+            let name = match &tokens[tokens.len() - 2].value {
+                TokenValue::Identifier(TokenIdentifierType::Generic(name)) => name.as_str(),
+                TokenValue::Identifier(TokenIdentifierType::Type(name)) => name.to_string(),
+                _ => unreachable!("child types are named by an identifier")
+            }.to_string();
+            ChildType {
+                name,
+                internal,
+                span: self.span(child_type.range().expect("parsed child types have tokens"))
+            }
+        });
+
+        let span = match &child_type {
+            Some(child_type) => child_type.span.to(&self.statement_span(node, &token)),
+            None => self.statement_span(node, &token)
+        };
+
+        if let Some(menu) = children.iter().find(|child| matches!(child.value, StatementValue::Menu(_))) {
+            if name != "menu" {
+                return Err(Diagnostic::error(Code::InvalidMenu, "menus can only be in root or collective definitions", menu.span.clone()));
+            }
+        }
+
+        let mut object = Object {
+            name,
+            name_span: self.span(token.range.clone()),
+            children,
+            block: block.map(|block| self.braces(block)),
+            arguments,
+            arguments_span: arglist.and_then(SyntaxNode::range).map(|range| self.span(range)),
+            setters: Vec::new(),
+            signals: Vec::new(),
+            child_type,
+            layout: Vec::new(),
+            accessible: Vec::new(),
+            classes: Vec::new()
+        };
+        self.lower_setters(node, &mut object);
+        if object.name == "menu" {
+            let menu = self.lower_menu(MenuKind::Menu, object, span.clone())?;
+            return Ok(Statement {
+                value: StatementValue::Menu(menu),
+                span,
+                doc: None
+            });
+        }
+
+        Ok(Statement {
+            value: StatementValue::Object(object),
+            span,
+            doc: None
+        })
+    }
+
+    // The setters of an object, sorted into its properties, signals, layout and accessible properties and style classes
+    fn lower_setters(&mut self, node: &SyntaxNode, object: &mut Object) {
+        for setter in node.nodes().filter(|child| child.kind == SyntaxKind::Setter) {
+            // The property of a layout or accessible setter is its second token, `.layout` or `.accessible` itself is left out
+            let (prefix, setter_token) = match setter.tokens().nth(1) {
+                Some(property @ Token { value: TokenValue::Setter(_), .. }) => (Some(Parser::head(setter)), property.clone()),
+                _ => (None, Parser::head(setter))
+            };
+            if let (None, TokenValue::Setter(setter_name)) = (&prefix, &setter_token.value) {
+                if setter_name == "on" {
+                    object.signals.extend(self.lower_signal(setter, &setter_token));
+                    continue;
+                }
+                if setter_name == "class" {
+                    object.classes.extend(self.lower_classes(setter));
+                    continue;
+                }
+            }
+            let setter_arglist = setter.node(SyntaxKind::ArgList).expect("parsed setters have an argument list");
+            let value = if let Some(binding) = setter_arglist.node(SyntaxKind::Binding) {
+                match self.lower_binding(setter_arglist, binding) {
+                    Some(binding) => SetterValue::Binding(binding),
+                    None => continue
+                }
+            } else if let Some(object) = setter_arglist.node(SyntaxKind::Object) {
+                let count = Parser::arguments(setter_arglist).len() + setter_arglist.nodes().count();
+                if count != 1 {
+                    self.errors.push(Diagnostic::error(Code::InvalidValue, format!("expected 1 argument, got {}", count), self.span(setter_arglist.range().unwrap_or(setter_token.range))));
+                    continue;
+                }
+                match self.lower_object(object) {
+                    Ok(Statement { value: StatementValue::Menu(_), span, .. }) => {
+                        self.errors.push(
+                            Diagnostic::error(Code::InvalidMenu, "menus can't be constructed in place", span)
+                                .with_help("give the menu an id and refer to it with '&'")
+                        );
+                        continue;
+                    },
+                    Ok(statement) => SetterValue::Object(Box::new(statement)),
+                    Err(err) => {
+                        self.errors.push(err);
+                        continue;
                     }
                 }
-                
-                Ok(Statement {
-                    value: StatementValue::Object(
-                        Object {
-                            arguments,
-                            name: name.clone(),
-                            children,
-                            setters
-                        }
-                    ),
-                    range: range.clone()
+            } else if Parser::arguments(setter_arglist).len() != 1 || setter_arglist.node(SyntaxKind::NamedArgument).is_some() {
+                // Whether the setter takes them is only known once its definition is
+                let positional = self.literals(setter_arglist);
+                let mut named = Vec::new();
+                for argument in setter_arglist.nodes().filter(|child| child.kind == SyntaxKind::NamedArgument) {
+                    let tokens: Vec<&Token> = argument.tokens().collect();
+                    let name = match &tokens[0].value {
+                        TokenValue::Identifier(TokenIdentifierType::Generic(name)) => name.as_str(),
+                        TokenValue::Identifier(TokenIdentifierType::Type(name)) => name.to_string(),
+                        _ => unreachable!("named arguments start with an identifier")
+                    }.to_string();
+                    if let Some(value) = Value::from(tokens[2]) {
+                        named.push((name, self.span(tokens[0].range.clone()), Literal { value, span: self.span(tokens[2].range.clone()) }));
+                    }
+                }
+                SetterValue::Arguments(Arguments {
+                    positional,
+                    named,
+                    span: self.span(setter_arglist.range().unwrap_or(setter_token.range.clone()))
                 })
             } else {
-                Err((format!("expected argument list or block, found nothing"), range))
+                // An invalid literal has been reported already, anything else that isn't a literal is reported here
+                match self.literals(setter_arglist).pop() {
+                    Some(value) => SetterValue::Literal(value),
+                    None => continue
+                }
+            };
+
+            if let TokenValue::Setter(setter_name) = &setter_token.value {
+                let setter_value = Setter {
+                    name: setter_name.clone(),
+                    name_span: self.span(setter_token.range.clone()),
+                    value,
+                    span: self.statement_span(setter, &Parser::head(setter))
+                };
+                match prefix.map(|prefix| prefix.value) {
+                    Some(TokenValue::Setter(prefix)) if prefix == "layout" => object.layout.push(setter_value),
+                    Some(_) => object.accessible.push(setter_value),
+                    None => object.setters.push(setter_value)
+                }
             }
-        } else {
-            Err((format!("expected generic identifier, found type identifier"), range))
         }
     }
 
-    fn parse_statement(&mut self) -> Option<Result<Statement, (String, Range<usize>)>> {
-        if let Some(token) = self.tokens.get(self.index) {
-            match &token.value {
-                TokenValue::Definition(definition) => {
-                    let definition = definition.clone();
-                    Some(self.definition(definition, token.range.clone()))
+    // Menus are parsed as objects and checked here. Their arguments are a shorthand for the label and, for items, the
+    // action.
+    fn lower_menu(&self, kind: MenuKind, object: Object, span: Span) -> Result<Menu, Diagnostic> {
+        if let Some(child_type) = &object.child_type {
+            return Err(Diagnostic::error(Code::InvalidMenu, format!("'{}' can't have a child type", kind.to_string()), child_type.span.clone()));
+        }
+        let extra = object.signals.first().map(|signal| &signal.span)
+            .or(object.classes.first().map(|(_, span)| span))
+            .or(object.layout.first().map(|setter| &setter.span))
+            .or(object.accessible.first().map(|setter| &setter.span));
+        if let Some(extra) = extra {
+            return Err(Diagnostic::error(Code::InvalidMenu, format!("'{}' can only be given an id and menu attributes", kind.to_string()), extra.clone()));
+        }
+
+        let positional: &[&str] = match kind {
+            MenuKind::Menu => &[],
+            MenuKind::Item => &["label", "action"],
+            MenuKind::Section | MenuKind::Submenu => &["label"]
+        };
+        if object.arguments.len() > positional.len() {
+            let span = object.arguments_span.clone().unwrap_or(object.name_span.clone());
+            return Err(
+                Diagnostic::error(Code::InvalidMenu, match positional.len() {
+                    0 => format!("'{}' takes no args, {} given", kind.to_string(), object.arguments.len()),
+                    count => format!("'{}' takes at most {} args, {} given", kind.to_string(), count, object.arguments.len())
+                }, span)
+                    .with_note(match kind {
+                        MenuKind::Menu => "menus don't have attributes, they belong to the sections, submenus and items of a menu",
+                        MenuKind::Item => "the arguments of an item are its label and action",
+                        _ => "the argument of a section or submenu is its label"
+                    })
+            );
+        }
+
+        let mut id = None;
+        let mut attributes: Vec<(String, Literal)> = positional.iter().map(|name| name.to_string()).zip(object.arguments).collect();
+        for setter in object.setters {
+            let literal = match setter.value {
+                SetterValue::Literal(literal) => literal,
+                _ => return Err(Diagnostic::error(Code::InvalidMenu, format!("'{}' can only be given a literal", setter.name), setter.span))
+            };
+            if setter.name == "id" && kind != MenuKind::Item {
+                match literal.value {
+                    Value::String(value) => id = Some(value),
+                    value => return Err(Diagnostic::error(Code::InvalidMenu, format!("'id' expects String, found {}", value.to_string()), literal.span))
+                }
+                continue;
+            }
+            if kind == MenuKind::Menu {
+                return Err(
+                    Diagnostic::error(Code::InvalidMenu, "'menu' can only be given an id", setter.name_span)
+                        .with_note("menus don't have attributes, they belong to the sections, submenus and items of a menu")
+                );
+            }
+            if !MENU_ATTRIBUTES.contains(&setter.name.as_str()) {
+                let diagnostic = Diagnostic::error(Code::InvalidMenu, format!("unknown menu attribute '{}'", setter.name), setter.name_span);
+                return Err(diagnostic.with_suggestion(&setter.name, MENU_ATTRIBUTES.iter().copied()));
+            }
+            if attributes.iter().any(|(name, _)| *name == setter.name) {
+                return Err(Diagnostic::error(Code::InvalidMenu, format!("'{}' is given more than once", setter.name), setter.name_span));
+            }
+            attributes.push((setter.name, literal));
+        }
+
+        // The target is passed to the action and can be of any type, every other attribute is a string
+        for (name, literal) in &attributes {
+            match (name.as_str(), &literal.value) {
+                (_, Value::String(_)) => (),
+                ("target", Value::Integer(_) | Value::Float(_) | Value::Bool(_)) => (),
+                ("target", Value::Translatable(_)) => return Err(Diagnostic::error(Code::InvalidMenu, "'target' can't be translatable", literal.span.clone())),
+                (_, Value::Translatable(_)) => (),
+                (name, value) => return Err(Diagnostic::error(Code::InvalidMenu, format!("'{}' expects String, found {}", name, value.to_string()), literal.span.clone()))
+            }
+        }
+
+        let mut children = Vec::new();
+        for child in object.children {
+            match child.value {
+                StatementValue::Object(_) if kind == MenuKind::Item => {
+                    return Err(Diagnostic::error(Code::InvalidMenu, "items can't have children", child.span));
                 },
-                TokenValue::Directive(directive) => {
-                    let directive = directive.clone();
-                    Some(self.directive(directive, token.range.clone()))
+                StatementValue::Object(child_object) => match MenuKind::from(&child_object.name) {
+                    Some(child_kind) if child_kind != MenuKind::Menu => children.push(self.lower_menu(child_kind, child_object, child.span)?),
+                    _ => return Err(Diagnostic::error(Code::InvalidMenu, format!("expected section, submenu or item, found '{}'", child_object.name), child_object.name_span))
                 },
-                TokenValue::Identifier(identifier) => {
-                    let identifier = identifier.clone();
-                    Some(self.object(identifier, token.range.clone()))
+                StatementValue::Menu(_) => {
+                    return Err(
+                        Diagnostic::error(Code::InvalidMenu, "menus can't be nested", child.span)
+                            .with_help("use 'submenu' for a menu inside of a menu")
+                    );
                 },
-                TokenValue::Comment => None,
-                _ => Some(Err(( format!("unexpected {}", token.to_string()), token.range.clone() )))
+                _ => return Err(Diagnostic::error(Code::InvalidMenu, format!("found {} inside of a menu", child.to_string()), child.span))
+            }
+        }
+
+        Ok(Menu {
+            kind,
+            id,
+            attributes,
+            children,
+            span
+        })
+    }
+
+    fn lower_statement(&mut self, node: &SyntaxNode) -> Result<Statement, Diagnostic> {
+        match node.kind {
+            SyntaxKind::Definition => self.lower_definition(node),
+            SyntaxKind::PropertyDefinition => self.lower_property(node),
+            SyntaxKind::Directive => self.lower_directive(node),
+            SyntaxKind::Object => self.lower_object(node),
+            _ => unreachable!("only statement nodes are lowered")
+        }
+    }
+
+    fn lower(&mut self, file: &SyntaxNode) {
+        for node in file.nodes().filter(|node| node.kind != SyntaxKind::Error) {
+            match self.lower_statement(node) {
+                Ok(statement) => {
+                    match &statement.value {
+                        StatementValue::Definition(_) | StatementValue::Header(_) | StatementValue::Include(_) | StatementValue::Requires(..) => self.statements.push(statement),
+                        _ => self.errors.push(Diagnostic::error(Code::MisplacedStatement, format!("found {} on top level. Only object definitions and directives are allowed here.", statement.to_string()), statement.span)),
+                    }
+                },
+                Err(err) => self.errors.push(err)
             }
-        } else {
-            Some(Err((format!("expected definition, directive, or identifier, found nothing"), self.statements[self.statements.len()-1].range.clone())))
         }
     }
 
     // Pubs
-    pub fn new(tokens: Vec<Token>, filename: String) -> Parser {
-        return Parser {
+    pub fn new(tokens: Vec<Token>, file: FileId, filename: String) -> Parser {
+        Parser {
             statements: Vec::new(),
+            syntax: SyntaxNode::new(SyntaxKind::File),
             index: 0,
             tokens,
-            filename
+            file,
+            filename,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            nodes: Vec::new()
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), (String, Range<usize>)> {
-        loop {
-            if self.index >= self.tokens.len() {
-                break Ok(())
-            }
-            match self.parse_statement() {
-                Some(result) => {
-                    match result {
-                        Ok(statement) => {
-                            match &statement.value {
-                                StatementValue::Definition(_) | StatementValue::Header(_) | StatementValue::Include(_) => self.statements.push(statement),
-                                _ => return Err(( format!("found {} on top level. Only object definitions and directives are allowed here.", statement.to_string()), statement.range )),
-                            }
-                        },
-                        Err(err) => return Err(err)
-                    }
-                },
-                None => continue
-            }
+    // Parses every statement. A statement with an error is skipped up to the end of its block or the next definition or
+    // directive, so one run reports every error while `syntax` and `statements` hold everything that could be parsed.
+    pub fn parse(&mut self) -> Result<(), Vec<Diagnostic>> {
+        self.nodes.push(SyntaxNode::new(SyntaxKind::File));
+        while self.peek().is_some() {
+            self.recovering_statement();
+        }
+        let file = self.nodes.pop().expect("the file node is never finished");
+        self.lower(&file);
+        self.syntax = file;
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            let mut errors = std::mem::take(&mut self.errors);
+            errors.sort_by_key(|err| err.span().map_or(0, |span| span.range.start));
+            Err(errors)
         }
     }
 }
@@ -6,9 +6,9 @@ use super::parser::{
     Statement,
     StatementValue
 };
-use super::util::{check_error, get_include_path};
-use std::ops::Range;
-use std::fs;
+use super::diagnostic::{Diagnostic, Code};
+use super::source::SourceMap;
+use super::compiler::FileLoader;
 
 
 // TODO: Warn about a file being included multiple times.
@@ -17,38 +17,52 @@ use std::fs;
 
 pub struct Preprocessor {
     pub statements: Vec<Statement>,
+    // The warnings of the included files
+    pub warnings: Vec<Diagnostic>
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Preprocessor { 
 
     // Pubs
-    pub fn preprocess(&mut self, input: Vec<Statement>, included_files: Vec<String>) -> Result<(), (String, Range<usize>)> {
+
+    // Replaces every include with the statements of the included file. Included files are added to the source map, so
+    // their diagnostics and the spans of their statements point into the right file.
+    pub fn preprocess(&mut self, input: Vec<Statement>, source_map: &mut SourceMap, loader: &dyn FileLoader) -> Result<(), Vec<Diagnostic>> {
         for statement in input {
             match statement.value {
                 StatementValue::Include(path) => {
-                    if let Some(path) = get_include_path(&path) {
-                        match fs::read_to_string(&path) {
+                    if let Some(path) = loader.resolve(&path) {
+                        let including = statement.span.file;
+                        let is_recursive = source_map.get(including).name == path
+                            || source_map.include_chain(including).iter().any(|include| source_map.get(include.file).name == path);
+                        if is_recursive {
+                            return Err(vec![Diagnostic::error(Code::RecursiveInclude, format!("recursive include of '{}'", path), statement.span)]);
+                        }
+
+                        match loader.load(&path) {
                             Ok(content) => {
-                                if included_files.iter().any(|n| n == &path) {
-                                    return Err((format!("recursive include of '{}'", path), (1..0)));
+                                let file = source_map.add(path.clone(), content.clone(), Some(statement.span));
+                                let mut lexer = Lexer::new(content, file);
+                                let mut errors = lexer.lex(false).err().unwrap_or_default();
+                                let mut parser = Parser::new(lexer.tokens, file, path);
+                                errors.extend(parser.parse().err().unwrap_or_default());
+                                self.warnings.append(&mut parser.warnings);
+                                if !errors.is_empty() {
+                                    return Err(errors);
                                 }
-                            
-                                let mut lexer = Lexer::new(content.clone());
-                                check_error(lexer.lex(false), &path, &content);
-                                let mut parser = Parser::new(lexer.tokens, included_files.last().unwrap().clone());
-                                check_error(parser.parse(), &path, &content);
 
-                                let mut included_files = included_files.clone();
-                                included_files.push(path);
-
-                                if let Err(err) = self.preprocess(parser.statements, included_files) {
-                                    return Err(err);
-                                }
+                                self.preprocess(parser.statements, source_map, loader)?
                             },
-                            Err(err) => return Err((String::from(err.to_string()), 1..0))
+                            Err(err) => return Err(vec![Diagnostic::error(Code::IncludeNotReadable, format!("could not read '{}': {}", path, err), statement.span)])
                         }
                     } else {
-                        return Err((format!("could not find file '{}' in lib directory or current working directory", path), (1..0)));
+                        return Err(vec![Diagnostic::error(Code::IncludeNotFound, format!("could not find included file '{}'", path), statement.span)]);
                     }
                 },
                 _ => {
@@ -62,6 +76,7 @@ impl Preprocessor {
     pub fn new() -> Self {
         Self {
             statements: Vec::new(),
+            warnings: Vec::new()
         }
     } 
 }
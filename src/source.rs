@@ -0,0 +1,62 @@
+use std::ops::Range;
+
+// Every file the compiler reads is added to the source map, so a span can point into any of them. Files that were
+// included remember the `#include` that included them, which gives the include chain shown with diagnostics.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file: FileId,
+    pub range: Range<usize>
+}
+
+impl Span {
+    pub fn new(file: FileId, range: Range<usize>) -> Self {
+        Self { file, range }
+    }
+
+    // The span from the start of this one to the end of another one in the same file
+    pub fn to(&self, other: &Span) -> Span {
+        Span::new(self.file, self.range.start..other.range.end)
+    }
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub content: String,
+    pub included_from: Option<Span>
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: String, content: String, included_from: Option<Span>) -> FileId {
+        self.files.push(SourceFile { name, content, included_from });
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    // The `#include`s that led to a file, innermost first
+    pub fn include_chain(&self, file: FileId) -> Vec<&Span> {
+        let mut chain = Vec::new();
+        let mut current = self.get(file).included_from.as_ref();
+        while let Some(span) = current {
+            chain.push(span);
+            current = self.get(span.file).included_from.as_ref();
+        }
+        chain
+    }
+}
@@ -0,0 +1,117 @@
+use super::lexer::Token;
+use std::ops::Range;
+
+// The concrete syntax tree built by the parser. Every token the parser was given ends up in it, in source order, so if
+// the tokens were lexed losslessly the tree maps back to the exact source, whitespace and comments included.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    File,               // the whole input
+    Definition,         // @MyDefinition -> Parent { ... }, @template("MyWindow", parent: Parent) { ... } .setter(value)
+    PropertyDefinition, // @ChildProp("name", String)
+    Inherits,           // -> Parent, -> (ParentA, ParentB)
+    Directive,          // #include "file"
+    Object,             // MyObject(args) { ... } .setter(value)
+    Setter,             // .setter(value), .layout.setter(value), .accessible.setter(value)
+    Block,              // { ... }
+    ArgList,            // (a, b)
+    NamedArgument,      // name: value, inside an argument list
+    Binding,            // bind source.property, inside an argument list
+    ObjectType,         // Object("GtkWidget"), inside an argument list
+    ChildType,          // start:, internal vbox:, in front of an object
+    Error               // input that could not be parsed, its error has already been reported
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token)
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>
+}
+
+impl SyntaxNode {
+    pub fn new(kind: SyntaxKind) -> Self {
+        Self {
+            kind,
+            children: Vec::new()
+        }
+    }
+
+    // Child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None
+        })
+    }
+
+    // The first child node of the given kind
+    pub fn node(&self, kind: SyntaxKind) -> Option<&SyntaxNode> {
+        self.nodes().find(|node| node.kind == kind)
+    }
+
+    // Child tokens, without trivia
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+            _ => None
+        })
+    }
+
+    // Every token in the node and its descendants, trivia included, in source order
+    pub fn descendant_tokens(&self) -> Box<dyn Iterator<Item = &Token> + '_> {
+        Box::new(self.children.iter().flat_map(|child| -> Box<dyn Iterator<Item = &Token>> {
+            match child {
+                SyntaxElement::Node(node) => node.descendant_tokens(),
+                SyntaxElement::Token(token) => Box::new(std::iter::once(token))
+            }
+        }))
+    }
+
+    // The span from the first to the last token that isn't trivia
+    pub fn range(&self) -> Option<Range<usize>> {
+        let mut tokens = self.descendant_tokens().filter(|token| !token.is_trivia());
+        let first = tokens.next()?;
+        let last = tokens.last().unwrap_or(first);
+        Some(first.range.start..last.range.end)
+    }
+
+    // The source text of every token in the node. For a losslessly lexed file this is the whole source.
+    pub fn text(&self, source: &str) -> String {
+        self.descendant_tokens().map(|token| &source[token.range.clone()]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::super::source::SourceMap;
+
+    // Lexes losslessly and parses the source, errors included, and gives back the text of the syntax tree
+    fn round_trip(source: &str) -> String {
+        let file = SourceMap::new().add("test.gui".to_string(), source.to_string(), None);
+        let mut lexer = Lexer::new(source.to_string(), file);
+        let _ = lexer.lex(true);
+        let mut parser = Parser::new(lexer.tokens, file, "test".to_string());
+        let _ = parser.parse();
+        parser.syntax.text(source)
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let source = "#include \"gtk-4.0\"\n\n/// A label\n@GtkLabel -> GtkWidget {\n  @ChildArg(\"label\", String) // the text\n}\n\n/* roots */\n@root {\n  GtkLabel(_(\"Hello\"))\n    .wrap(true)\n}\n";
+        assert_eq!(round_trip(source), source);
+    }
+
+    #[test]
+    fn lossless_round_trip_with_errors() {
+        let source = "#inlcude \"gtk-4.0\"\n@root {\n  GtkLabel(, 99999999999999999999) $\n    .wrap(\n}\n";
+        assert_eq!(round_trip(source), source);
+    }
+}
@@ -1,49 +1,52 @@
-use std::ops::Range;
 use std::fs;
 
 const LIB_PATH: &str =  "/usr/share/gtk-ui";
 
-pub fn check_error(result: Result<(), (String, Range<usize>)>, file: &String, file_content: &String) {
-    if let Err(err) = result {
-        if err.1.start > err.1.end {
-            println!("\x1b[1;31mError:\x1b[0m {} (in {})", err.0, file);
-        } else {
-            match get_position_from_char_index(err.1.start, file_content) {
-                Ok((line, char)) => {
-                    println!("\x1b[1;31mError:\x1b[0m {} (line {}, char {}, in {})", err.0, line, char, file);
-                },
-                Err(message) => println!("\x1b[1;31mError:\x1b[0m {}", message)
-            }
-        }
-        std::process::exit(1);
-    }
-}
-
-pub fn get_position_from_char_index(char_index: usize, file_content: &String) -> Result<(usize, usize), &str> {
+// Turns a byte offset into a 1-based line and character (not byte) column
+pub fn get_position_from_char_index(char_index: usize, file_content: &str) -> Result<(usize, usize), &str> {
     // Quick sanity check
-    if char_index >= file_content.len() {
+    if char_index > file_content.len() || !file_content.is_char_boundary(char_index) {
         Err("character index bigger than file content (something horrible must have gone wrong)")
     } else {
-        let mut line_count = 1;
-        let mut char_count = 1;
-        for i in 0..char_index {
-            char_count += 1;
-            if file_content.chars().nth(i).unwrap() == '\n' {
-                line_count += 1;
-                char_count = 1;
-            }
-        }
-        Ok((line_count, char_count))
+        let before = &file_content[..char_index];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Ok((before.matches('\n').count() + 1, before[line_start..].chars().count() + 1))
     }
 }
 
-pub fn get_include_path(path: &String) -> Option<String> {
-    let lib_file_path = format!("{LIB_PATH}/{path}.gui");
-    if fs::metadata(&lib_file_path).is_ok() {
-        Some(lib_file_path)
-    } else if fs::metadata(&path).is_ok() {
-        Some(path.clone())
-    } else {
-        None
+// The names of the files an include can refer to, an include of "lib" finds a file called "lib" or "lib.gui"
+pub fn include_names(path: &str) -> [String; 2] {
+    [path.to_string(), format!("{path}.gui")]
+}
+
+// Files in the library directory come before the ones in the working directory
+pub fn get_include_path(path: &str) -> Option<String> {
+    let library = include_names(path).map(|name| format!("{LIB_PATH}/{name}"));
+    library.into_iter()
+        .chain(include_names(path))
+        .find(|name| fs::metadata(name).is_ok_and(|metadata| metadata.is_file()))
+}
+
+// Levenshtein distance between two strings, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
     }
+    previous[b.len()]
+}
+
+// The candidate closest to `name`, as long as it is close enough to be a likely typo
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
 }
@@ -0,0 +1,202 @@
+// A small XML tree and writer for the generated documents. Elements keep their attributes in the order they were added,
+// so the same input always gives the same output.
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+    // Written as is, for the XML given to `#header`
+    Raw(String)
+}
+
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>
+}
+
+// How the document is laid out. Without an indentation, the document is written on a single line.
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub indent: Option<String>
+}
+
+impl Format {
+    pub fn pretty(indent: usize) -> Self {
+        Self {
+            indent: Some(" ".repeat(indent))
+        }
+    }
+
+    pub fn minified() -> Self {
+        Self {
+            indent: None
+        }
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::pretty(2)
+    }
+}
+
+pub fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    escape_text(value)
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+        .replace('\t', "&#9;")
+}
+
+// Text that looks like markup, like a Pango markup label, stays readable in a CDATA section. A CDATA section can't
+// contain "]]>", so such text is escaped instead.
+fn write_text(text: &str, result: &mut String) {
+    if text.contains('<') && !text.contains("]]>") {
+        result.push_str("<![CDATA[");
+        result.push_str(text);
+        result.push_str("]]>");
+    } else {
+        result.push_str(&escape_text(text));
+    }
+}
+
+impl Element {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            children: Vec::new()
+        }
+    }
+
+    pub fn with_attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_child(mut self, child: Element) -> Self {
+        self.children.push(Node::Element(child));
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.children.push(Node::Text(text.to_string()));
+        self
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(attribute, _)| attribute == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn push(&mut self, node: Node) {
+        self.children.push(node);
+    }
+
+    // Elements that only hold text are kept on one line, so the text isn't changed by indentation
+    fn write(&self, format: &Format, depth: usize, result: &mut String) {
+        let indentation = format.indent.as_ref().map(|indent| indent.repeat(depth)).unwrap_or_default();
+        result.push_str(&indentation);
+        result.push('<');
+        result.push_str(&self.name);
+        for (name, value) in &self.attributes {
+            result.push(' ');
+            result.push_str(name);
+            result.push_str("=\"");
+            result.push_str(&escape_attribute(value));
+            result.push('"');
+        }
+
+        if self.children.is_empty() {
+            result.push_str("/>");
+        } else if self.children.iter().all(|child| matches!(child, Node::Text(_))) {
+            result.push('>');
+            for child in &self.children {
+                if let Node::Text(text) = child {
+                    write_text(text, result);
+                }
+            }
+            result.push_str("</");
+            result.push_str(&self.name);
+            result.push('>');
+        } else {
+            result.push('>');
+            for child in &self.children {
+                if format.indent.is_some() {
+                    result.push('\n');
+                }
+                match child {
+                    Node::Element(element) => element.write(format, depth + 1, result),
+                    Node::Text(text) => {
+                        if let Some(indent) = &format.indent {
+                            result.push_str(&indent.repeat(depth + 1));
+                        }
+                        write_text(text, result);
+                    },
+                    Node::Raw(raw) => {
+                        if let Some(indent) = &format.indent {
+                            result.push_str(&indent.repeat(depth + 1));
+                        }
+                        result.push_str(raw.trim());
+                    }
+                }
+            }
+            if format.indent.is_some() {
+                result.push('\n');
+                result.push_str(&indentation);
+            }
+            result.push_str("</");
+            result.push_str(&self.name);
+            result.push('>');
+        }
+    }
+
+    // The element as a whole document, with the XML declaration in front of it
+    pub fn to_document(&self, format: &Format) -> String {
+        let mut result = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        if format.indent.is_some() {
+            result.push('\n');
+        }
+        self.write(format, 0, &mut result);
+        result.push('\n');
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(element: &Element) -> String {
+        element.to_document(&Format::minified())
+    }
+
+    #[test]
+    fn escapes_text() {
+        let element = Element::new("property").with_text("Fish & Chips > Salad");
+        assert_eq!(document(&element), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><property>Fish &amp; Chips &gt; Salad</property>\n");
+    }
+
+    #[test]
+    fn escapes_attributes() {
+        let element = Element::new("object").with_attribute("id", "a\"b<c>&d\n\te\r");
+        assert_eq!(document(&element), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><object id=\"a&quot;b&lt;c&gt;&amp;d&#10;&#9;e&#13;\"/>\n");
+    }
+
+    #[test]
+    fn writes_markup_in_cdata() {
+        let element = Element::new("property").with_text("<b>Bold</b> & co");
+        assert_eq!(document(&element), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><property><![CDATA[<b>Bold</b> & co]]></property>\n");
+    }
+
+    #[test]
+    fn escapes_markup_that_would_end_cdata() {
+        let element = Element::new("property").with_text("<b>a]]>b</b>");
+        assert_eq!(document(&element), "<?xml version=\"1.0\" encoding=\"UTF-8\"?><property>&lt;b&gt;a]]&gt;b&lt;/b&gt;</property>\n");
+    }
+}
@@ -0,0 +1,689 @@
+use gtk_ui::compiler::{compile, MemoryLoader, Options};
+use gtk_ui::diagnostic::Code;
+use gtk_ui::xml::Format;
+
+// The definitions the tests build on, included like a project includes its library
+const LIB: &str = r#"
+@GtkWidget {
+  @InlineProp("id", String)
+  @ChildProp("tooltip-text", String)
+  @ChildProp("visible", Bool)
+  @Signal("notify")
+  @AccessibleProp("label", String)
+  @AccessibleRelation("labelled-by", Object("GtkWidget"))
+  @AccessibleState("checked", Bool)
+  @AccessibleRole("checkbox")
+}
+
+@GtkLabel -> GtkWidget {
+  @ChildArg("label", String)
+  @ChildProp("mnemonic-widget", Object("GtkWidget"))
+}
+
+@GtkEntry -> GtkWidget {}
+
+@GtkAdjustment {
+  @ChildProp("lower", Double)
+  @ChildProp("upper", Double)
+}
+
+@GtkScale -> GtkWidget {
+  @ChildProp("adjustment", Object("GtkAdjustment"))
+}
+
+@GtkButton -> GtkWidget {
+  @ChildArg("label", String)
+  @Signal("clicked")
+}
+
+@GtkCheckButton -> GtkWidget {
+  @ChildProp("active", Bool)
+}
+
+@GtkHeaderBar -> GtkWidget {
+  @ChildType("start")
+  @ChildType("end")
+}
+
+@GtkGrid -> GtkWidget {
+  @LayoutProp("column", Int)
+  @LayoutProp("row", Int)
+}
+
+@GtkMenuButton -> GtkWidget {
+  @ChildProp("menu-model", Object("GMenuModel"))
+}
+
+@GtkWindow -> GtkWidget {
+  @ChildProp("title", String)
+  @ChildProp("default-widget", Object("GtkWidget"))
+  @ChildType("titlebar")
+}
+
+@GtkStringList {
+  @Element("item", "items", String)
+}
+
+@GtkSizeGroup {
+  @Element("widget", "widgets", name: Object("GtkWidget"))
+}
+
+@AdwMessageDialog -> GtkWindow {
+  @Element("response", "responses", String, id: String, appearance: Optional(String), enabled: Optional(Bool))
+}
+
+@GtkBox -> GtkWidget {
+  @ChildProp("spacing", Int)
+}
+"#;
+
+fn options() -> Options {
+    Options::new("main.gui")
+        .with_loader(MemoryLoader::new().with_file("lib.gui", LIB))
+        .with_format(Format::minified())
+}
+
+fn source(root: &str) -> String {
+    format!("#include \"lib\"\n{root}")
+}
+
+// The document generated from the root of the source
+fn generate(root: &str) -> String {
+    match compile(&source(root), &options()) {
+        Ok(mut output) => output.documents.remove("main").expect("the root is generated"),
+        Err(diagnostics) => panic!("expected the source to compile, got {:#?}", diagnostics)
+    }
+}
+
+// The codes of the errors and warnings the source fails with
+fn fail(root: &str) -> Vec<Code> {
+    match compile(&source(root), &options()) {
+        Ok(output) => panic!("expected the source to fail, got {:#?}", output.documents),
+        Err(diagnostics) => diagnostics.iter().map(|diagnostic| diagnostic.code).collect()
+    }
+}
+
+#[test]
+fn object_without_definition() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkLable("Hello")
+  }
+}
+"#), [Code::UnknownDefinition]);
+}
+
+#[test]
+fn translatable_strings() {
+    let document = generate(r#"
+@root {
+  // Translators: the greeting on the start page
+  GtkLabel(_("Hello"))
+    .tooltip-text(C_("tooltip", "Says hello"))
+}
+"#);
+    assert!(document.contains(r#"<property name="label" translatable="yes" comments="the greeting on the start page">Hello</property>"#), "{}", document);
+    assert!(document.contains(r#"<property name="tooltip-text" translatable="yes" context="tooltip">Says hello</property>"#), "{}", document);
+}
+
+#[test]
+fn translatable_inline_value() {
+    assert_eq!(fail(r#"
+@root {
+  GtkLabel("Hello")
+    .id(_("label"))
+}
+"#), [Code::InvalidInlineValue]);
+}
+
+#[test]
+fn signals() {
+    let document = generate(r#"
+@root {
+  GtkBox {
+    GtkButton("Save")
+      .id("save")
+      .on("clicked", "on_save_clicked", swapped: true, object: "save")
+      .on("notify::label", "on_label_changed", after: false)
+  }
+}
+"#);
+    assert!(document.contains(r#"<signal name="clicked" handler="on_save_clicked" swapped="yes" object="save"/>"#), "{}", document);
+    assert!(document.contains(r#"<signal name="notify::label" handler="on_label_changed" after="no"/>"#), "{}", document);
+}
+
+#[test]
+fn unknown_signal() {
+    assert_eq!(fail(r#"
+@root {
+  GtkButton("Save")
+    .on("clikced", "on_save_clicked")
+}
+"#), [Code::UnknownSignal]);
+}
+
+#[test]
+fn signal_object_without_id() {
+    assert_eq!(fail(r#"
+@root {
+  GtkButton("Save")
+    .on("clicked", "on_save_clicked", object: "nope")
+}
+"#), [Code::InvalidReference]);
+}
+
+#[test]
+fn invalid_signal_option() {
+    assert_eq!(fail(r#"
+@root {
+  GtkButton("Save")
+    .on("clicked", "on_save_clicked", swapped: "yes")
+}
+"#), [Code::InvalidSignal]);
+}
+
+#[test]
+fn bindings() {
+    let document = generate(r#"
+@root {
+  GtkBox {
+    GtkCheckButton {}
+      .id("toggle")
+    GtkLabel("Details")
+      .visible(bind toggle.active, sync-create, invert-boolean)
+      .tooltip-text(bind toggle.tooltip-markup)
+  }
+}
+"#);
+    assert!(document.contains(r#"<property name="visible" bind-source="toggle" bind-property="active" bind-flags="sync-create|invert-boolean"/>"#), "{}", document);
+    // tooltip-markup isn't declared, so it's bound without a check
+    assert!(document.contains(r#"<property name="tooltip-text" bind-source="toggle" bind-property="tooltip-markup"/>"#), "{}", document);
+}
+
+#[test]
+fn binding_without_source() {
+    assert_eq!(fail(r#"
+@root {
+  GtkLabel("Details")
+    .visible(bind toggle.active)
+}
+"#), [Code::InvalidBinding]);
+}
+
+#[test]
+fn binding_of_another_type() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkCheckButton {}
+      .id("toggle")
+    GtkLabel("Details")
+      .tooltip-text(bind toggle.active)
+  }
+}
+"#), [Code::TypeMismatch]);
+}
+
+#[test]
+fn binding_of_inline_value() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkCheckButton {}
+      .id("toggle")
+    GtkLabel("Details")
+      .id(bind toggle.id)
+  }
+}
+"#), [Code::InvalidBinding]);
+}
+
+#[test]
+fn unknown_binding_flag() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkCheckButton {}
+      .id("toggle")
+    GtkLabel("Details")
+      .visible(bind toggle.active, sync_create)
+  }
+}
+"#), [Code::MalformedBinding]);
+}
+
+#[test]
+fn references() {
+    let document = generate(r#"
+@root {
+  GtkBox {
+    GtkLabel("_Name")
+      .mnemonic-widget(&entry1)
+    GtkEntry {}
+      .id("entry1")
+  }
+}
+"#);
+    assert!(document.contains(r#"<property name="mnemonic-widget">entry1</property>"#), "{}", document);
+}
+
+#[test]
+fn reference_without_object() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkLabel("_Name")
+      .mnemonic-widget(&entry2)
+    GtkEntry {}
+      .id("entry1")
+  }
+}
+"#), [Code::InvalidReference]);
+}
+
+#[test]
+fn reference_of_another_class() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkLabel("_Name")
+      .mnemonic-widget(&adjustment)
+    GtkAdjustment {}
+      .id("adjustment")
+  }
+}
+"#), [Code::InvalidReference]);
+}
+
+#[test]
+fn duplicate_id() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkEntry {}
+      .id("entry1")
+    GtkEntry {}
+      .id("entry1")
+  }
+}
+"#), [Code::DuplicateId]);
+}
+
+#[test]
+fn inline_objects() {
+    let document = generate(r#"
+@root {
+  GtkScale {}
+    .adjustment(GtkAdjustment {}.lower(0).upper(100))
+}
+"#);
+    assert!(document.contains(r#"<property name="adjustment"><object class="GtkAdjustment"><property name="lower">0.0</property><property name="upper">100.0</property></object></property>"#), "{}", document);
+}
+
+#[test]
+fn inline_object_of_another_class() {
+    assert_eq!(fail(r#"
+@root {
+  GtkScale {}
+    .adjustment(GtkEntry {})
+}
+"#), [Code::TypeMismatch]);
+}
+
+#[test]
+fn inline_object_with_layout() {
+    assert_eq!(fail(r#"
+@root {
+  GtkLabel("_Name")
+    .mnemonic-widget(GtkEntry {}.layout.column(0))
+}
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn inline_object_with_other_arguments() {
+    assert_eq!(fail(r#"
+@root {
+  GtkScale {}
+    .adjustment(GtkAdjustment {}, 1)
+}
+"#), [Code::InvalidValue]);
+}
+
+#[test]
+fn child_types() {
+    let document = generate(r#"
+@root {
+  GtkHeaderBar {
+    start: GtkButton("Open")
+    internal title: GtkLabel("Title")
+  }
+}
+"#);
+    assert!(document.contains(r#"<child type="start"><object class="GtkButton">"#), "{}", document);
+    assert!(document.contains(r#"<child internal-child="title"><object class="GtkLabel">"#), "{}", document);
+}
+
+#[test]
+fn undeclared_child_type() {
+    assert_eq!(fail(r#"
+@root {
+  GtkHeaderBar {
+    strat: GtkButton("Open")
+  }
+}
+"#), [Code::UnknownChildType]);
+}
+
+#[test]
+fn child_type_of_root_object() {
+    assert_eq!(fail(r#"
+@root {
+  start: GtkButton("Open")
+}
+"#), [Code::UnknownChildType]);
+}
+
+#[test]
+fn layout_properties() {
+    let document = generate(r#"
+@root {
+  GtkGrid {
+    GtkButton("Open")
+      .layout.column(0)
+      .layout.row(1)
+  }
+}
+"#);
+    assert!(document.contains(r#"<child><object class="GtkButton"><property name="label">Open</property><layout><property name="column">0</property><property name="row">1</property></layout></object></child>"#), "{}", document);
+}
+
+#[test]
+fn layout_property_not_declared_by_parent() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    GtkButton("Open")
+      .layout.column(0)
+  }
+}
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn layout_property_of_another_type() {
+    assert_eq!(fail(r#"
+@root {
+  GtkGrid {
+    GtkButton("Open")
+      .layout.column("first")
+  }
+}
+"#), [Code::TypeMismatch]);
+}
+
+#[test]
+fn style_classes() {
+    let document = generate(r#"
+@root {
+  GtkButton("Save")
+    .class("suggested-action", "pill")
+    .class("flat")
+}
+"#);
+    assert!(document.contains(r#"<style><class name="suggested-action"/><class name="pill"/><class name="flat"/></style>"#), "{}", document);
+}
+
+#[test]
+fn style_classes_of_non_widget() {
+    assert_eq!(fail(r#"
+@root {
+  GtkAdjustment {}
+    .class("flat")
+}
+"#), [Code::InvalidStyle]);
+}
+
+#[test]
+fn style_class_that_is_not_a_string() {
+    assert_eq!(fail(r#"
+@root {
+  GtkButton("Save")
+    .class(true)
+}
+"#), [Code::InvalidValue]);
+}
+
+#[test]
+fn accessibility() {
+    let document = generate(r#"
+@root {
+  GtkBox {
+    GtkLabel("Remember me")
+      .id("remember-label")
+    GtkCheckButton {}
+      .accessible.role("checkbox")
+      .accessible.label(_("Remember"))
+      .accessible.labelled-by(&remember-label)
+      .accessible.checked(true)
+  }
+}
+"#);
+    assert!(document.contains(r#"<object class="GtkCheckButton"><property name="accessible-role">checkbox</property><accessibility><property name="label" translatable="yes">Remember</property><relation name="labelled-by">remember-label</relation><state name="checked">true</state></accessibility></object>"#), "{}", document);
+}
+
+#[test]
+fn undeclared_accessible_role() {
+    assert_eq!(fail(r#"
+@root {
+  GtkCheckButton {}
+    .accessible.role("button")
+}
+"#), [Code::UnknownRole]);
+}
+
+#[test]
+fn undeclared_accessible_state() {
+    assert_eq!(fail(r#"
+@root {
+  GtkCheckButton {}
+    .accessible.checkd(true)
+}
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn accessible_relation_without_object() {
+    assert_eq!(fail(r#"
+@root {
+  GtkCheckButton {}
+    .accessible.labelled-by(&remember-label)
+}
+"#), [Code::InvalidReference]);
+}
+
+#[test]
+fn accessible_relation_that_is_not_an_object() {
+    assert_eq!(fail(r#"
+@GtkSwitch -> GtkWidget {
+  @AccessibleRelation("controls", String)
+}
+"#), [Code::InvalidPropertyDefinition]);
+}
+
+#[test]
+fn menus() {
+    let document = generate(r#"
+@root {
+  menu {
+    section {
+      item(_("_New Window"), "app.new-window")
+        .accel("<Primary>n")
+      submenu(_("Zoom")) {
+        item(_("Zoom In"), "win.zoom")
+          .target(1)
+      }
+    }
+  }
+  .id("app-menu")
+  GtkMenuButton {}
+    .menu-model(&app-menu)
+}
+"#);
+    assert!(document.contains(concat!(
+        r#"<menu id="app-menu"><section><item><attribute name="label" translatable="yes">_New Window</attribute><attribute name="action">app.new-window</attribute>"#,
+        r#"<attribute name="accel"><![CDATA[<Primary>n]]></attribute></item><submenu><attribute name="label" translatable="yes">Zoom</attribute>"#,
+        r#"<item><attribute name="label" translatable="yes">Zoom In</attribute><attribute name="action">win.zoom</attribute><attribute name="target" type="i">1</attribute></item>"#,
+        r#"</submenu></section></menu>"#
+    )), "{}", document);
+    assert!(document.contains(r#"<property name="menu-model">app-menu</property>"#), "{}", document);
+}
+
+#[test]
+fn menu_inside_object() {
+    assert_eq!(fail(r#"
+@root {
+  GtkBox {
+    menu {}
+  }
+}
+"#), [Code::InvalidMenu]);
+}
+
+#[test]
+fn unknown_menu_attribute() {
+    assert_eq!(fail(r#"
+@root {
+  menu {
+    item("Quit", "app.quit")
+      .icn("application-exit")
+  }
+}
+"#), [Code::InvalidMenu]);
+}
+
+#[test]
+fn menu_given_to_widget_property() {
+    assert_eq!(fail(r#"
+@root {
+  menu {}
+  .id("app-menu")
+  GtkLabel("_Menu")
+    .mnemonic-widget(&app-menu)
+}
+"#), [Code::InvalidReference]);
+}
+
+#[test]
+fn templates() {
+    let output = compile(&source(r#"
+@template("MyWindow", parent: GtkWindow) {
+  titlebar: GtkHeaderBar {}
+  GtkButton("Ok")
+    .id("ok")
+    .on("clicked", "on_ok_clicked", object: "MyWindow")
+}
+.title(_("My Window"))
+.default-widget(&ok)
+"#), &options()).expect("the template compiles");
+    let document = &output.documents["MyWindow"];
+    assert!(document.contains(r#"<template class="MyWindow" parent="GtkWindow"><property name="title" translatable="yes">My Window</property><property name="default-widget">ok</property><child type="titlebar"><object class="GtkHeaderBar"/></child>"#), "{}", document);
+    assert!(document.contains(r#"<signal name="clicked" handler="on_ok_clicked" object="MyWindow"/>"#), "{}", document);
+}
+
+#[test]
+fn template_without_parent() {
+    assert_eq!(fail(r#"
+@template("MyWindow") {}
+"#), [Code::InvalidTemplate]);
+}
+
+#[test]
+fn template_named_like_root() {
+    let options = Options::new("MyWindow.gui").with_loader(MemoryLoader::new().with_file("lib.gui", LIB));
+    let codes: Vec<Code> = compile(&source(r#"
+@root {
+  GtkWindow {}
+}
+@template("MyWindow", parent: GtkWindow) {}
+"#), &options).unwrap_err().iter().map(|diagnostic| diagnostic.code).collect();
+    assert_eq!(codes, [Code::DuplicateDocument]);
+}
+
+#[test]
+fn template_with_layout() {
+    assert_eq!(fail(r#"
+@template("MyGrid", parent: GtkGrid) {}
+.layout.column(0)
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn template_with_undeclared_property() {
+    assert_eq!(fail(r#"
+@template("MyWindow", parent: GtkWindow) {}
+.titel("My Window")
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn custom_elements() {
+    let document = generate(r#"
+@root {
+  GtkStringList {}
+    .item("One")
+    .item(_("Two"))
+  GtkLabel("Name")
+    .id("name")
+  GtkSizeGroup {}
+    .widget(&name)
+  AdwMessageDialog {}
+    .response(_("_Cancel"), "cancel")
+    .response(_("_Delete"), "delete", appearance: "destructive", enabled: false)
+}
+"#);
+    assert!(document.contains(r#"<items><item>One</item><item translatable="yes">Two</item></items>"#), "{}", document);
+    assert!(document.contains(r#"<widgets><widget name="name"/></widgets>"#), "{}", document);
+    assert!(document.contains(r#"<responses><response translatable="yes" id="cancel">_Cancel</response><response translatable="yes" id="delete" appearance="destructive" enabled="false">_Delete</response></responses>"#), "{}", document);
+}
+
+#[test]
+fn custom_element_without_required_attribute() {
+    assert_eq!(fail(r#"
+@root {
+  AdwMessageDialog {}
+    .response(_("_Cancel"))
+}
+"#), [Code::ArgumentCount]);
+}
+
+#[test]
+fn custom_element_with_unknown_attribute() {
+    assert_eq!(fail(r#"
+@root {
+  AdwMessageDialog {}
+    .response(_("_Cancel"), "cancel", apperance: "destructive")
+}
+"#), [Code::UnknownProperty]);
+}
+
+#[test]
+fn custom_element_of_another_type() {
+    assert_eq!(fail(r#"
+@root {
+  GtkStringList {}
+    .item(1)
+}
+"#), [Code::TypeMismatch]);
+}
+
+#[test]
+fn custom_element_given_an_object() {
+    assert_eq!(fail(r#"
+@root {
+  GtkSizeGroup {}
+    .widget(GtkLabel("Name"))
+}
+"#), [Code::InvalidValue]);
+}